@@ -0,0 +1,122 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Default)]
+pub struct ArchiveStats {
+    pub files: usize,
+    pub bytes: AtomicU64,
+}
+
+/// Lists the files under `dir` (as paths relative to it) that match `pattern`, sorted.
+/// Without a pattern every file is included; `*` does not cross directories, `**` does.
+pub fn collect_files(dir: &Path, pattern: Option<&str>) -> Result<Vec<PathBuf>, String> {
+    let pattern = pattern
+        .map(|pattern| glob::Pattern::new(pattern).map_err(|err| format!("Invalid body_glob '{}': {}", pattern, err)))
+        .transpose()?;
+    let options = glob::MatchOptions {
+        require_literal_separator: true,
+        ..Default::default()
+    };
+
+    if !dir.is_dir() {
+        return Err(format!("body_dir '{}' is not a directory", dir.display()));
+    }
+
+    let mut files = Vec::new();
+    let mut pending = vec![PathBuf::new()];
+    while let Some(relative_dir) = pending.pop() {
+        let entries = std::fs::read_dir(dir.join(&relative_dir))
+            .map_err(|err| format!("Failed to read '{}': {}", dir.join(&relative_dir).display(), err))?;
+        for entry in entries {
+            let entry = entry.map_err(|err| err.to_string())?;
+            let relative = relative_dir.join(entry.file_name());
+            let file_type = entry.file_type().map_err(|err| err.to_string())?;
+            if file_type.is_dir() {
+                pending.push(relative);
+            } else if file_type.is_file()
+                && pattern.as_ref().is_none_or(|pattern| pattern.matches_path_with(&relative, options))
+            {
+                files.push(relative);
+            }
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+struct ChannelWriter {
+    sender: mpsc::Sender<io::Result<Vec<u8>>>,
+    stats: Arc<ArchiveStats>,
+}
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.sender
+            .blocking_send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "upload was cancelled"))?;
+        self.stats.bytes.fetch_add(buf.len() as u64, Ordering::Relaxed);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_archive(dir: &Path, files: &[PathBuf], writer: ChannelWriter) -> io::Result<()> {
+    let encoder = GzEncoder::new(BufWriter::with_capacity(CHUNK_SIZE, writer), Compression::default());
+    let mut archive = tar::Builder::new(encoder);
+    for file in files {
+        archive.append_path_with_name(dir.join(file), file)?;
+    }
+    archive.into_inner()?.finish()?.flush()
+}
+
+enum StreamState {
+    Pending(PathBuf, Vec<PathBuf>, Arc<ArchiveStats>),
+    Running(mpsc::Receiver<io::Result<Vec<u8>>>),
+}
+
+/// A request body that archives `files` as tar.gz while it is being sent. Archiving only
+/// starts once the body is first polled, and the compressed size is tallied in `stats`.
+pub fn tar_gz_body(dir: PathBuf, files: Vec<PathBuf>, stats: Arc<ArchiveStats>) -> reqwest::Body {
+    let stream = futures::stream::unfold(StreamState::Pending(dir, files, stats), |state| async move {
+        let mut receiver = match state {
+            StreamState::Pending(dir, files, stats) => {
+                let (sender, receiver) = mpsc::channel(8);
+                tokio::task::spawn_blocking(move || {
+                    let writer = ChannelWriter { sender: sender.clone(), stats };
+                    if let Err(err) = write_archive(&dir, &files, writer) {
+                        let _ = sender.blocking_send(Err(err));
+                    }
+                });
+                receiver
+            }
+            StreamState::Running(receiver) => receiver,
+        };
+        receiver.recv().await.map(|chunk| (chunk, StreamState::Running(receiver)))
+    });
+    reqwest::Body::wrap_stream(stream)
+}
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct ArchiveReport {
+    pub files: usize,
+    pub bytes: u64,
+}
+
+impl ArchiveStats {
+    pub fn report(&self) -> ArchiveReport {
+        ArchiveReport {
+            files: self.files,
+            bytes: self.bytes.load(Ordering::Relaxed),
+        }
+    }
+}
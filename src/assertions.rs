@@ -0,0 +1,135 @@
+use crate::diff;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// How many differences are listed when describing a mismatch.
+const MAX_REPORTED_DIFFERENCES: usize = 5;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct Assertions {
+    pub one_of: Option<Vec<Value>>,
+    /// Expected values keyed by JSON pointer, e.g. `"/data/id": 42`.
+    #[serde(flatten)]
+    pub pointers: BTreeMap<String, Value>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct AssertionOutcome {
+    pub name: String,
+    pub passed: bool,
+    pub message: String,
+}
+
+fn describe_differences(differences: &[diff::Difference]) -> String {
+    let mut lines: Vec<String> = differences.iter()
+        .take(MAX_REPORTED_DIFFERENCES)
+        .map(|difference| difference.to_string())
+        .collect();
+    if differences.len() > MAX_REPORTED_DIFFERENCES {
+        lines.push(format!("... and {} more", differences.len() - MAX_REPORTED_DIFFERENCES));
+    }
+    lines.join("; ")
+}
+
+fn check_one_of(candidates: &[Value], body: Option<&Value>) -> AssertionOutcome {
+    let name = "one_of".to_string();
+    let Some(body) = body else {
+        return AssertionOutcome { name, passed: false, message: "Response body is not JSON".to_string() };
+    };
+
+    let mut closest: Option<(usize, Vec<diff::Difference>)> = None;
+    for (index, candidate) in candidates.iter().enumerate() {
+        let differences = diff::diff_values(candidate, body);
+        if differences.is_empty() {
+            return AssertionOutcome { name, passed: true, message: format!("Matched one_of[{}]", index) };
+        }
+        if closest.as_ref().is_none_or(|(_, best)| differences.len() < best.len()) {
+            closest = Some((index, differences));
+        }
+    }
+
+    let message = match closest {
+        Some((index, differences)) => format!(
+            "Matched none of {} expected bodies; closest is one_of[{}]: {}",
+            candidates.len(), index, describe_differences(&differences)
+        ),
+        None => "'one_of' lists no expected bodies".to_string(),
+    };
+    AssertionOutcome { name, passed: false, message }
+}
+
+fn check_pointer(pointer: &str, expected: &Value, body: Option<&Value>) -> AssertionOutcome {
+    let name = pointer.to_string();
+    if !pointer.starts_with('/') {
+        return AssertionOutcome { name, passed: false, message: "Unknown assertion (JSON pointers start with '/')".to_string() };
+    }
+    let Some(body) = body else {
+        return AssertionOutcome { name, passed: false, message: "Response body is not JSON".to_string() };
+    };
+
+    match body.pointer(pointer) {
+        None => AssertionOutcome { name, passed: false, message: format!("Not found; expected {}", expected) },
+        Some(actual) => {
+            let mut differences = diff::diff_values(expected, actual);
+            for difference in &mut differences {
+                difference.path = format!("{}{}", pointer, difference.path);
+            }
+            if differences.is_empty() {
+                AssertionOutcome { name, passed: true, message: format!("Equals {}", expected) }
+            } else {
+                AssertionOutcome { name, passed: false, message: describe_differences(&differences) }
+            }
+        }
+    }
+}
+
+/// Compiles a schema given inline, or as the path of a JSON schema file.
+pub fn load_schema(schema: &Value) -> Result<jsonschema::Validator, String> {
+    let loaded;
+    let schema = match schema {
+        Value::String(path) => {
+            let data = std::fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read schema file '{}': {}", path, err))?;
+            loaded = serde_json::from_str::<Value>(&data)
+                .map_err(|err| format!("Invalid JSON in schema file '{}': {}", path, err))?;
+            &loaded
+        }
+        inline => inline,
+    };
+    jsonschema::validator_for(schema).map_err(|err| format!("Invalid schema: {}", err))
+}
+
+pub fn check_schema(validator: &jsonschema::Validator, body: Option<&Value>) -> AssertionOutcome {
+    let name = "schema".to_string();
+    let Some(body) = body else {
+        return AssertionOutcome { name, passed: false, message: "Response body is not JSON".to_string() };
+    };
+
+    let errors: Vec<String> = validator.iter_errors(body)
+        .map(|error| {
+            let path = error.instance_path().to_string();
+            let path = if path.is_empty() { "/".to_string() } else { path };
+            format!("{}: {}", path, error)
+        })
+        .collect();
+    if errors.is_empty() {
+        return AssertionOutcome { name, passed: true, message: "Valid".to_string() };
+    }
+    let mut lines: Vec<String> = errors.iter().take(MAX_REPORTED_DIFFERENCES).cloned().collect();
+    if errors.len() > MAX_REPORTED_DIFFERENCES {
+        lines.push(format!("... and {} more", errors.len() - MAX_REPORTED_DIFFERENCES));
+    }
+    AssertionOutcome { name, passed: false, message: lines.join("; ") }
+}
+
+pub fn evaluate(assertions: &Assertions, body: Option<&Value>) -> Vec<AssertionOutcome> {
+    let mut outcomes = Vec::new();
+    if let Some(candidates) = &assertions.one_of {
+        outcomes.push(check_one_of(candidates, body));
+    }
+    for (pointer, expected) in &assertions.pointers {
+        outcomes.push(check_pointer(pointer, expected, body));
+    }
+    outcomes
+}
@@ -0,0 +1,160 @@
+//! The `auth` field of a request, including the HTTP Digest (RFC 7616) challenge/response handshake.
+
+use md5::Md5;
+use reqwest::header::{HeaderValue, AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::StatusCode;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Auth {
+    Basic { username: String, password: Option<String> },
+    Bearer { token: String },
+    Digest { username: String, password: String },
+}
+
+/// The parameters of a `WWW-Authenticate: Digest ...` challenge.
+#[derive(Debug, Default)]
+struct Challenge {
+    realm: String,
+    nonce: String,
+    opaque: Option<String>,
+    algorithm: Option<String>,
+    qop: Option<String>,
+}
+
+/// Splits `key=value, key="quoted, value"` pairs; keys are lowercased.
+fn parse_params(text: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut rest = text.trim();
+    while let Some((key, after)) = rest.split_once('=') {
+        let key = key.trim().trim_start_matches(',').trim().to_lowercase();
+        let after = after.trim_start();
+        let (value, remaining) = match after.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+                let mut end = quoted.len();
+                while let Some((index, c)) = chars.next() {
+                    match c {
+                        '\\' => value.extend(chars.next().map(|(_, escaped)| escaped)),
+                        '"' => {
+                            end = index + 1;
+                            break;
+                        }
+                        other => value.push(other),
+                    }
+                }
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after.find(',').unwrap_or(after.len());
+                (after[..end].trim().to_string(), &after[end..])
+            }
+        };
+        params.push((key, value));
+        rest = remaining.trim_start().trim_start_matches(',');
+    }
+    params
+}
+
+fn parse_challenge(header: &str) -> Option<Challenge> {
+    let start = header.to_lowercase().find("digest ")?;
+    let mut challenge = Challenge::default();
+    for (key, value) in parse_params(&header[start + "digest ".len()..]) {
+        match key.as_str() {
+            "realm" => challenge.realm = value,
+            "nonce" => challenge.nonce = value,
+            "opaque" => challenge.opaque = Some(value),
+            "algorithm" => challenge.algorithm = Some(value),
+            "qop" => challenge.qop = Some(value),
+            _ => {}
+        }
+    }
+    (!challenge.nonce.is_empty()).then_some(challenge)
+}
+
+fn hash(algorithm: &str, data: &str) -> String {
+    if algorithm.starts_with("SHA-256") {
+        format!("{:x}", Sha256::digest(data.as_bytes()))
+    } else {
+        format!("{:x}", Md5::digest(data.as_bytes()))
+    }
+}
+
+/// The `Authorization` header answering `challenge`.
+fn authorization(challenge: &Challenge, username: &str, password: &str, method: &str, uri: &str) -> Result<String, String> {
+    let algorithm = challenge.algorithm.as_deref().unwrap_or("MD5").to_uppercase();
+    if !["MD5", "MD5-SESS", "SHA-256", "SHA-256-SESS"].contains(&algorithm.as_str()) {
+        return Err(format!("Unsupported digest algorithm '{}'", algorithm));
+    }
+    let qop = match &challenge.qop {
+        Some(offered) if offered.split(',').any(|qop| qop.trim() == "auth") => Some("auth"),
+        Some(offered) => return Err(format!("Unsupported digest qop '{}'", offered)),
+        None => None,
+    };
+    let cnonce = format!("{:016x}", rand::random::<u64>());
+    let nc = "00000001";
+
+    let mut ha1 = hash(&algorithm, &format!("{}:{}:{}", username, challenge.realm, password));
+    if algorithm.ends_with("-SESS") {
+        ha1 = hash(&algorithm, &format!("{}:{}:{}", ha1, challenge.nonce, cnonce));
+    }
+    let ha2 = hash(&algorithm, &format!("{}:{}", method, uri));
+    let response = match qop {
+        Some(qop) => hash(&algorithm, &format!("{}:{}:{}:{}:{}:{}", ha1, challenge.nonce, nc, cnonce, qop, ha2)),
+        None => hash(&algorithm, &format!("{}:{}:{}", ha1, challenge.nonce, ha2)),
+    };
+
+    let mut header = format!(
+        r#"Digest username="{}", realm="{}", nonce="{}", uri="{}", algorithm={}, response="{}""#,
+        username, challenge.realm, challenge.nonce, uri, challenge.algorithm.as_deref().unwrap_or("MD5"), response
+    );
+    if let Some(qop) = qop {
+        header.push_str(&format!(r#", qop={}, nc={}, cnonce="{}""#, qop, nc, cnonce));
+    }
+    if let Some(opaque) = &challenge.opaque {
+        header.push_str(&format!(r#", opaque="{}""#, opaque));
+    }
+    Ok(header)
+}
+
+/// Why a digest handshake could not be completed.
+pub enum DigestError {
+    Http(reqwest::Error),
+    Handshake(String),
+}
+
+/// Sends `request`; if the server answers 401 with a Digest challenge, sends `retry` (an identical
+/// copy) again with the computed `Authorization` header. Any other first response is returned as is.
+pub async fn send_with_digest(
+    client: &reqwest::Client,
+    request: reqwest::Request,
+    mut retry: reqwest::Request,
+    username: &str,
+    password: &str,
+) -> Result<reqwest::Response, DigestError> {
+    let response = client.execute(request).await.map_err(DigestError::Http)?;
+    if response.status() != StatusCode::UNAUTHORIZED {
+        return Ok(response);
+    }
+    let Some(challenge) = response.headers().get_all(WWW_AUTHENTICATE).iter()
+        .filter_map(|value| value.to_str().ok())
+        .find_map(parse_challenge)
+    else {
+        return Ok(response);
+    };
+
+    let url = retry.url();
+    let uri = match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    };
+    let header = authorization(&challenge, username, password, retry.method().as_str(), &uri)
+        .map_err(DigestError::Handshake)?;
+    let value = HeaderValue::from_str(&header)
+        .map_err(|_| DigestError::Handshake("Digest credentials contain characters not allowed in a header".to_string()))?;
+    retry.headers_mut().insert(AUTHORIZATION, value);
+    client.execute(retry).await.map_err(DigestError::Http)
+}
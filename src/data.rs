@@ -0,0 +1,146 @@
+//! Data-driven requests: a request with `data` runs once per row of a CSV or JSON file.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+
+static PLACEHOLDER_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{(\w+)\}\}").unwrap());
+
+type Row = BTreeMap<String, Value>;
+
+/// A value as text inside a longer string.
+fn cell(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// CSV cells that read as a JSON number, boolean, or null keep that type when used on their own.
+fn csv_value(text: &str) -> Value {
+    match serde_json::from_str::<Value>(text) {
+        Ok(value) if value.is_number() || value.is_boolean() || value.is_null() => value,
+        _ => Value::String(text.to_string()),
+    }
+}
+
+/// Reads a `.csv` file (the first line names the columns) or a JSON array of objects.
+fn load_rows(path: &str) -> Result<Vec<Row>, String> {
+    let rows = if path.to_lowercase().ends_with(".csv") {
+        let mut reader = csv::Reader::from_path(path).map_err(|err| format!("Failed to read data file '{}': {}", path, err))?;
+        let columns = reader.headers().map_err(|err| format!("Invalid CSV in data file '{}': {}", path, err))?.clone();
+        reader.records()
+            .map(|record| {
+                let record = record.map_err(|err| format!("Invalid CSV in data file '{}': {}", path, err))?;
+                Ok(columns.iter().zip(record.iter()).map(|(column, value)| (column.to_string(), csv_value(value))).collect())
+            })
+            .collect::<Result<Vec<Row>, String>>()?
+    } else {
+        let text = fs::read_to_string(path).map_err(|err| format!("Failed to read data file '{}': {}", path, err))?;
+        let value: Value = serde_json::from_str(&text).map_err(|err| format!("Invalid JSON in data file '{}': {}", path, err))?;
+        let Value::Array(items) = value else {
+            return Err(format!("Data file '{}' must contain a JSON array of objects", path));
+        };
+        items.iter()
+            .map(|item| match item {
+                Value::Object(map) => Ok(map.clone().into_iter().collect()),
+                other => Err(format!("Data file '{}' must contain a JSON array of objects, found {}", path, other)),
+            })
+            .collect::<Result<Vec<Row>, String>>()?
+    };
+    if rows.is_empty() {
+        return Err(format!("Data file '{}' has no rows", path));
+    }
+    Ok(rows)
+}
+
+/// Fields holding JSON values (sent or compared), where a placeholder keeps the type of its cell.
+const JSON_FIELDS: &[&str] = &["body", "body_base", "body_patch", "assert"];
+
+/// Replaces `{{column}}` in every string of `value`; placeholders naming no column are left alone.
+/// With `typed`, a string that is nothing but one placeholder becomes the row's value itself, keeping its type.
+fn substitute(value: &mut Value, row: &Row, typed: bool) {
+    match value {
+        Value::String(text) => {
+            if typed
+                && let Some(caps) = PLACEHOLDER_REGEX.captures(text)
+                && caps[0].len() == text.len()
+                && let Some(cell) = row.get(&caps[1])
+            {
+                *value = cell.clone();
+            } else if text.contains("{{") {
+                *text = PLACEHOLDER_REGEX.replace_all(text, |caps: &regex::Captures| {
+                    row.get(&caps[1]).map(cell).unwrap_or_else(|| caps[0].to_string())
+                }).to_string();
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(|item| substitute(item, row, typed)),
+        Value::Object(map) => map.values_mut().for_each(|child| substitute(child, row, typed)),
+        _ => {}
+    }
+}
+
+/// Fills one copy of a request from `row`: cells keep their type in the JSON body fields, `assert`,
+/// and `graphql.variables`, and are inserted as text everywhere else (the URL, headers, params, form).
+fn fill(request: &mut Value, row: &Row) {
+    let Value::Object(fields) = request else {
+        return substitute(request, row, false);
+    };
+    for (field, value) in fields.iter_mut() {
+        match (field.as_str(), value) {
+            ("graphql", Value::Object(graphql)) => {
+                for (key, value) in graphql.iter_mut() {
+                    substitute(value, row, key == "variables");
+                }
+            }
+            (field, value) => substitute(value, row, JSON_FIELDS.contains(&field)),
+        }
+    }
+}
+
+/// Replaces each request that has `data` by one copy per row, named `name[1]`, `name[2]`, ...
+pub fn expand(requests: Vec<Value>) -> Result<Vec<Value>, String> {
+    let mut expanded = Vec::with_capacity(requests.len());
+    for mut request in requests {
+        let Some(Value::String(path)) = request.as_object_mut().and_then(|map| map.remove("data")) else {
+            expanded.push(request);
+            continue;
+        };
+        let name = request.get("name").and_then(Value::as_str).unwrap_or("Unnamed").to_string();
+        for (index, row) in load_rows(&path)?.iter().enumerate() {
+            let mut copy = request.clone();
+            fill(&mut copy, row);
+            copy["name"] = Value::String(format!("{}[{}]", name, index + 1));
+            expanded.push(copy);
+        }
+    }
+    Ok(expanded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn cells_keep_their_type_only_in_the_body() {
+        let row: Row = [("id".to_string(), csv_value("5"))].into_iter().collect();
+        let mut request = json!({
+            "url": "http://localhost/users/{{id}}",
+            "headers": { "X-Id": "{{id}}" },
+            "params": { "id": "{{id}}" },
+            "body": { "id": "{{id}}", "label": "user {{id}}" },
+            "graphql": { "query": "{{id}}", "variables": { "id": "{{id}}" } }
+        });
+        fill(&mut request, &row);
+        assert_eq!(request, json!({
+            "url": "http://localhost/users/5",
+            "headers": { "X-Id": "5" },
+            "params": { "id": "5" },
+            "body": { "id": 5, "label": "user 5" },
+            "graphql": { "query": "5", "variables": { "id": 5 } }
+        }));
+    }
+}
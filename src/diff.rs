@@ -0,0 +1,90 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Difference {
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expected: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        match (&self.expected, &self.actual) {
+            (Some(expected), Some(actual)) => write!(f, "{}: expected {}, got {}", path, expected, actual),
+            (Some(expected), None) => write!(f, "{}: missing (expected {})", path, expected),
+            (None, Some(actual)) => write!(f, "{}: unexpected {}", path, actual),
+            (None, None) => write!(f, "{}", path),
+        }
+    }
+}
+
+fn escape_pointer(key: &str) -> String {
+    key.replace('~', "~0").replace('/', "~1")
+}
+
+/// Numbers compare by value, so `1` and `1.0` are equal; everything else compares structurally.
+pub fn values_equal(expected: &Value, actual: &Value) -> bool {
+    match (expected, actual) {
+        (Value::Number(a), Value::Number(b)) => a == b || a.as_f64() == b.as_f64(),
+        (Value::Array(a), Value::Array(b)) => {
+            a.len() == b.len() && a.iter().zip(b).all(|(a, b)| values_equal(a, b))
+        }
+        (Value::Object(a), Value::Object(b)) => {
+            a.len() == b.len() && a.iter().all(|(key, a)| b.get(key).is_some_and(|b| values_equal(a, b)))
+        }
+        (a, b) => a == b,
+    }
+}
+
+fn collect(path: &str, expected: &Value, actual: &Value, out: &mut Vec<Difference>) {
+    match (expected, actual) {
+        (Value::Object(expected_map), Value::Object(actual_map)) => {
+            for (key, expected_value) in expected_map {
+                let child = format!("{}/{}", path, escape_pointer(key));
+                match actual_map.get(key) {
+                    Some(actual_value) => collect(&child, expected_value, actual_value, out),
+                    None => out.push(Difference { path: child, expected: Some(expected_value.clone()), actual: None }),
+                }
+            }
+            for (key, actual_value) in actual_map {
+                if !expected_map.contains_key(key) {
+                    out.push(Difference {
+                        path: format!("{}/{}", path, escape_pointer(key)),
+                        expected: None,
+                        actual: Some(actual_value.clone()),
+                    });
+                }
+            }
+        }
+        (Value::Array(expected_items), Value::Array(actual_items)) => {
+            for index in 0..expected_items.len().max(actual_items.len()) {
+                let child = format!("{}/{}", path, index);
+                match (expected_items.get(index), actual_items.get(index)) {
+                    (Some(e), Some(a)) => collect(&child, e, a, out),
+                    (e, a) => out.push(Difference { path: child, expected: e.cloned(), actual: a.cloned() }),
+                }
+            }
+        }
+        (expected, actual) => {
+            if !values_equal(expected, actual) {
+                out.push(Difference {
+                    path: path.to_string(),
+                    expected: Some(expected.clone()),
+                    actual: Some(actual.clone()),
+                });
+            }
+        }
+    }
+}
+
+/// Lists every JSON pointer at which `actual` differs from `expected`.
+pub fn diff_values(expected: &Value, actual: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    collect("", expected, actual, &mut differences);
+    differences
+}
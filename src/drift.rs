@@ -0,0 +1,74 @@
+use crate::RequestResult;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Debug, Serialize)]
+pub struct DriftReport {
+    pub state_file: String,
+    pub baseline_created: bool,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+/// Hashes the parsed body, so formatting and key order differences are ignored.
+fn body_hash(body: &Option<serde_json::Value>) -> String {
+    let normalized = serde_json::to_string(body).unwrap_or_default();
+    format!("{:x}", Sha256::digest(normalized.as_bytes()))
+}
+
+/// Compares response hashes with the state file and rewrites it with this run's hashes.
+/// Entries for requests that exist in the file but were not run (e.g. filtered out) are kept.
+pub fn check_drift(path: &Path, results: &[RequestResult], known_names: &[String]) -> io::Result<DriftReport> {
+    let previous: Option<BTreeMap<String, String>> = match fs::read_to_string(path) {
+        Ok(data) => Some(serde_json::from_str(&data).map_err(|err| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("Invalid hash state file '{}': {}", path.display(), err))
+        })?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+
+    let mut current = BTreeMap::new();
+    for result in results.iter().filter(|r| !r.skipped) {
+        current.insert(result.name.clone(), body_hash(&result.response_body));
+    }
+
+    let mut report = DriftReport {
+        state_file: path.display().to_string(),
+        baseline_created: previous.is_none(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    let mut next_state = current.clone();
+    if let Some(previous) = previous {
+        for (name, hash) in &current {
+            match previous.get(name) {
+                None => report.added.push(name.clone()),
+                Some(old) if old != hash => report.changed.push(name.clone()),
+                Some(_) => {}
+            }
+        }
+        for (name, hash) in previous {
+            if current.contains_key(&name) {
+                continue;
+            }
+            if known_names.contains(&name) {
+                next_state.insert(name, hash);
+            } else {
+                report.removed.push(name);
+            }
+        }
+    }
+
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string_pretty(&next_state)?)?;
+    Ok(report)
+}
@@ -0,0 +1,416 @@
+//! A small, side-effect free expression language used by `invariants`, `run_if`, and `skip_if`.
+//!
+//! ```text
+//! expr    := or
+//! or      := and ("||" and)*
+//! and     := compare ("&&" compare)*
+//! compare := sum (("==" | "!=" | "<" | "<=" | ">" | ">=") sum)?
+//! sum     := product (("+" | "-") product)*
+//! product := unary (("*" | "/" | "%") unary)*
+//! unary   := ("-" | "!") unary | postfix
+//! postfix := primary ("." name | "[" expr "]")*
+//! primary := number | string | true | false | null | name | name "(" args ")" | "(" expr ")"
+//! ```
+//!
+//! `||` and `&&` short-circuit: the right side is not evaluated once the left decides the result.
+//! Names refer to variables; a `{{NAME}}` placeholder left over because its variable is not set
+//! reads as `""`. Functions are `sum`, `len`, `min`, `max`, `abs`, and `round`.
+//! `sum`, `min`, and `max` take an array of numbers, or an array of objects plus the field to read.
+
+use crate::diff;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Name(String),
+    Op(&'static str),
+}
+
+const OPERATORS: &[&str] = &[
+    "==", "!=", "<=", ">=", "&&", "||", "<", ">", "+", "-", "*", "/", "%", "!", "(", ")", "[", "]", ".", ",",
+];
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse().map_err(|_| format!("Invalid number '{}'", text))?;
+            tokens.push(Token::Number(number));
+        } else if c == '"' || c == '\'' {
+            let quote = c;
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err("Unterminated string".to_string()),
+                    Some(&ch) if ch == quote => break,
+                    Some('\\') => {
+                        i += 1;
+                        match chars.get(i) {
+                            Some('n') => text.push('\n'),
+                            Some('t') => text.push('\t'),
+                            Some(&other) => text.push(other),
+                            None => return Err("Unterminated string".to_string()),
+                        }
+                    }
+                    Some(&ch) => text.push(ch),
+                }
+                i += 1;
+            }
+            i += 1;
+            tokens.push(Token::Str(text));
+        } else if c == '{' && chars.get(i + 1) == Some(&'{') {
+            let close = (i + 2..chars.len().saturating_sub(1))
+                .find(|&end| chars[end] == '}' && chars[end + 1] == '}')
+                .ok_or_else(|| "Unterminated '{{'".to_string())?;
+            i = close + 2;
+            tokens.push(Token::Str(String::new()));
+        } else if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i += 1;
+            }
+            tokens.push(Token::Name(chars[start..i].iter().collect()));
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let op = OPERATORS.iter()
+                .find(|op| rest.starts_with(**op))
+                .ok_or_else(|| format!("Unexpected character '{}'", c))?;
+            i += op.len();
+            tokens.push(Token::Op(op));
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    position: usize,
+    variables: &'a HashMap<String, Value>,
+    /// Read variables that are not set as `null` instead of failing.
+    unknown_as_null: bool,
+}
+
+fn as_number(value: &Value, context: &str) -> Result<f64, String> {
+    value.as_f64().ok_or_else(|| format!("{} expects a number, got {}", context, value))
+}
+
+fn number(value: f64) -> Value {
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        json!(value as i64)
+    } else {
+        json!(value)
+    }
+}
+
+fn truthy(value: &Value) -> Result<bool, String> {
+    value.as_bool().ok_or_else(|| format!("Expected a boolean, got {}", value))
+}
+
+/// The numbers in an array, or in the `field` of each object in it.
+fn numbers_of(value: &Value, field: Option<&Value>, function: &str) -> Result<Vec<f64>, String> {
+    let items = value.as_array().ok_or_else(|| format!("{}() expects an array, got {}", function, value))?;
+    let field = field
+        .map(|field| field.as_str().ok_or_else(|| format!("{}() expects a field name, got {}", function, field)))
+        .transpose()?;
+    items.iter()
+        .map(|item| match field {
+            Some(field) => as_number(item.get(field).unwrap_or(&Value::Null), function),
+            None => as_number(item, function),
+        })
+        .collect()
+}
+
+fn call(function: &str, args: Vec<Value>) -> Result<Value, String> {
+    let arg = |index: usize| args.get(index).ok_or_else(|| format!("{}() is missing argument {}", function, index + 1));
+    match function {
+        "sum" => Ok(number(numbers_of(arg(0)?, args.get(1), function)?.iter().sum())),
+        "min" | "max" => {
+            let values = numbers_of(arg(0)?, args.get(1), function)?;
+            let folded = values.iter().copied().reduce(|a, b| if function == "min" { a.min(b) } else { a.max(b) });
+            folded.map(number).ok_or_else(|| format!("{}() of an empty array", function))
+        }
+        "len" => match arg(0)? {
+            Value::Array(items) => Ok(json!(items.len())),
+            Value::Object(map) => Ok(json!(map.len())),
+            Value::String(text) => Ok(json!(text.chars().count())),
+            other => Err(format!("len() expects an array, object, or string, got {}", other)),
+        },
+        "abs" => Ok(number(as_number(arg(0)?, function)?.abs())),
+        "round" => {
+            let value = as_number(arg(0)?, function)?;
+            let digits = args.get(1).map(|d| as_number(d, function)).transpose()?.unwrap_or(0.0);
+            let factor = 10f64.powi(digits as i32);
+            Ok(number((value * factor).round() / factor))
+        }
+        other => Err(format!("Unknown function '{}'", other)),
+    }
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        if matches!(self.peek(), Some(Token::Op(found)) if *found == op) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("Expected '{}'", op))
+        }
+    }
+
+    /// Moves past the operand that starts here without evaluating it: up to the next of `stops`,
+    /// or the `)`, `]`, or `,` that closes the enclosing group, outside any brackets of its own.
+    fn skip(&mut self, stops: &[&str]) {
+        let mut depth = 0usize;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::Op("(" | "[") => depth += 1,
+                Token::Op(")" | "]" | ",") if depth == 0 => return,
+                Token::Op(")" | "]") => depth -= 1,
+                Token::Op(op) if depth == 0 && stops.contains(op) => return,
+                _ => {}
+            }
+            self.position += 1;
+        }
+    }
+
+    fn or(&mut self) -> Result<Value, String> {
+        let mut left = self.and()?;
+        while self.eat("||") {
+            if truthy(&left)? {
+                self.skip(&["||"]);
+            } else {
+                left = json!(truthy(&self.and()?)?);
+            }
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Value, String> {
+        let mut left = self.compare()?;
+        while self.eat("&&") {
+            if truthy(&left)? {
+                left = json!(truthy(&self.compare()?)?);
+            } else {
+                self.skip(&["||", "&&"]);
+            }
+        }
+        Ok(left)
+    }
+
+    fn compare(&mut self) -> Result<Value, String> {
+        let left = self.sum()?;
+        for op in ["==", "!=", "<=", ">=", "<", ">"] {
+            if self.eat(op) {
+                let right = self.sum()?;
+                let result = match op {
+                    "==" => diff::values_equal(&left, &right),
+                    "!=" => !diff::values_equal(&left, &right),
+                    _ => {
+                        let ordering = match (&left, &right) {
+                            (Value::String(a), Value::String(b)) => a.cmp(b),
+                            _ => as_number(&left, op)?
+                                .partial_cmp(&as_number(&right, op)?)
+                                .ok_or_else(|| "Cannot compare NaN".to_string())?,
+                        };
+                        match op {
+                            "<" => ordering.is_lt(),
+                            "<=" => ordering.is_le(),
+                            ">" => ordering.is_gt(),
+                            _ => ordering.is_ge(),
+                        }
+                    }
+                };
+                return Ok(json!(result));
+            }
+        }
+        Ok(left)
+    }
+
+    fn sum(&mut self) -> Result<Value, String> {
+        let mut left = self.product()?;
+        loop {
+            if self.eat("+") {
+                let right = self.product()?;
+                left = match (&left, &right) {
+                    (Value::String(a), Value::String(b)) => json!(format!("{}{}", a, b)),
+                    _ => number(as_number(&left, "+")? + as_number(&right, "+")?),
+                };
+            } else if self.eat("-") {
+                let right = self.product()?;
+                left = number(as_number(&left, "-")? - as_number(&right, "-")?);
+            } else {
+                return Ok(left);
+            }
+        }
+    }
+
+    fn product(&mut self) -> Result<Value, String> {
+        let mut left = self.unary()?;
+        loop {
+            let op = if self.eat("*") {
+                "*"
+            } else if self.eat("/") {
+                "/"
+            } else if self.eat("%") {
+                "%"
+            } else {
+                return Ok(left);
+            };
+            let a = as_number(&left, op)?;
+            let b = as_number(&self.unary()?, op)?;
+            if op != "*" && b == 0.0 {
+                return Err("Division by zero".to_string());
+            }
+            left = number(match op {
+                "*" => a * b,
+                "/" => a / b,
+                _ => a % b,
+            });
+        }
+    }
+
+    fn unary(&mut self) -> Result<Value, String> {
+        if self.eat("-") {
+            let value = self.unary()?;
+            return Ok(number(-as_number(&value, "-")?));
+        }
+        if self.eat("!") {
+            let value = self.unary()?;
+            return Ok(json!(!truthy(&value)?));
+        }
+        self.postfix()
+    }
+
+    fn postfix(&mut self) -> Result<Value, String> {
+        let mut value = self.primary()?;
+        loop {
+            if self.eat(".") {
+                let Some(Token::Name(field)) = self.peek().cloned() else {
+                    return Err("Expected a field name after '.'".to_string());
+                };
+                self.position += 1;
+                value = value.get(&field).cloned().unwrap_or(Value::Null);
+            } else if self.eat("[") {
+                let index = self.or()?;
+                self.expect("]")?;
+                value = match &index {
+                    Value::String(key) => value.get(key).cloned(),
+                    other => {
+                        let index = as_number(other, "[]")?;
+                        if index < 0.0 || index.fract() != 0.0 {
+                            return Err(format!("[] expects a non-negative whole number, got {}", other));
+                        }
+                        value.get(index as usize).cloned()
+                    }
+                }
+                .unwrap_or(Value::Null);
+            } else {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn primary(&mut self) -> Result<Value, String> {
+        let token = self.peek().cloned().ok_or_else(|| "Unexpected end of expression".to_string())?;
+        self.position += 1;
+        match token {
+            Token::Number(value) => Ok(number(value)),
+            Token::Str(text) => Ok(json!(text)),
+            Token::Op("(") => {
+                let value = self.or()?;
+                self.expect(")")?;
+                Ok(value)
+            }
+            Token::Name(name) => match name.as_str() {
+                "true" => Ok(json!(true)),
+                "false" => Ok(json!(false)),
+                "null" => Ok(Value::Null),
+                _ if self.eat("(") => {
+                    let mut args = Vec::new();
+                    if !self.eat(")") {
+                        loop {
+                            args.push(self.or()?);
+                            if self.eat(")") {
+                                break;
+                            }
+                            self.expect(",")?;
+                        }
+                    }
+                    call(&name, args)
+                }
+                _ => match self.variables.get(&name) {
+                    Some(value) => Ok(value.clone()),
+                    None if self.unknown_as_null => Ok(Value::Null),
+                    None => Err(format!("Unknown variable '{}'", name)),
+                },
+            },
+            Token::Op(op) => Err(format!("Unexpected '{}'", op)),
+        }
+    }
+}
+
+pub fn evaluate(source: &str, variables: &HashMap<String, Value>) -> Result<Value, String> {
+    parse(source, variables, false)
+}
+
+/// Like `evaluate`, for `run_if` and `skip_if`: a variable that was never captured reads as `null`.
+pub fn evaluate_condition(source: &str, variables: &HashMap<String, Value>) -> Result<Value, String> {
+    parse(source, variables, true)
+}
+
+fn parse(source: &str, variables: &HashMap<String, Value>, unknown_as_null: bool) -> Result<Value, String> {
+    let mut parser = Parser { tokens: tokenize(source)?, position: 0, variables, unknown_as_null };
+    let value = parser.or()?;
+    if parser.position < parser.tokens.len() {
+        return Err(format!("Unexpected trailing input in '{}'", source));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn logical_operators_short_circuit() {
+        let variables = HashMap::from([("items".to_string(), json!([1, 2]))]);
+        assert_eq!(evaluate_condition("missing == null || missing > 1", &variables), Ok(json!(true)));
+        assert_eq!(evaluate_condition("missing != null && missing > 1", &variables), Ok(json!(false)));
+        assert_eq!(evaluate("len(items) == 2 || items[0] / 0 > 1 || nope", &variables), Ok(json!(true)));
+        assert_eq!(evaluate("false && (items[5] > 1 || nope) || len(items) == 2", &variables), Ok(json!(true)));
+        assert_eq!(evaluate("false || nope", &variables), Err("Unknown variable 'nope'".to_string()));
+    }
+
+    #[test]
+    fn index_must_be_a_non_negative_whole_number() {
+        let variables = HashMap::from([("items".to_string(), json!([1, 2]))]);
+        assert_eq!(evaluate("items[1]", &variables), Ok(json!(2)));
+        assert_eq!(evaluate("items[-1]", &variables), Err("[] expects a non-negative whole number, got -1".to_string()));
+        assert_eq!(evaluate("items[0.5]", &variables), Err("[] expects a non-negative whole number, got 0.5".to_string()));
+    }
+}
@@ -0,0 +1,47 @@
+//! Syntax highlighting for the JSON bodies shown in the pretty output.
+
+use colored::*;
+
+/// Colors pretty-printed JSON: keys, strings, numbers, booleans, and null each get their own color.
+/// The input may be cut off anywhere (e.g. when truncated); an unterminated token is colored as far as it goes.
+pub fn json(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut output = String::with_capacity(text.len() * 2);
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '"' {
+            let start = i;
+            i += 1;
+            while i < chars.len() && chars[i] != '"' {
+                if chars[i] == '\\' {
+                    i += 1;
+                }
+                i += 1;
+            }
+            i = (i + 1).min(chars.len());
+            let token: String = chars[start..i].iter().collect();
+            let is_key = chars[i..].iter().find(|c| !c.is_whitespace()) == Some(&':');
+            output.push_str(&if is_key { token.cyan() } else { token.green() }.to_string());
+        } else if c == '-' || c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || matches!(chars[i], '-' | '+' | '.' | 'e' | 'E')) {
+                i += 1;
+            }
+            let token: String = chars[start..i].iter().collect();
+            output.push_str(&token.yellow().to_string());
+        } else if c.is_ascii_alphabetic() {
+            let start = i;
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            let token: String = chars[start..i].iter().collect();
+            let colored = if token.starts_with('n') { token.bright_black() } else { token.magenta() };
+            output.push_str(&colored.to_string());
+        } else {
+            output.push(c);
+            i += 1;
+        }
+    }
+    output
+}
@@ -0,0 +1,34 @@
+//! `before` / `after` hooks: shell commands run around a request (with `--allow-hooks`).
+
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::process::Stdio;
+
+/// Runs `command` with `sh -c`, with `env` added to the environment. A JSON object printed to
+/// stdout becomes captured variables; any other output is ignored. A non-zero exit is an error
+/// carrying the exit status and stderr.
+pub async fn run(command: &str, env: &[(&str, String)]) -> Result<BTreeMap<String, Value>, String> {
+    let output = tokio::process::Command::new("sh")
+        .arg("-c")
+        .arg(command)
+        .envs(env.iter().map(|(name, value)| (name, value)))
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .map_err(|err| format!("could not run '{}': {}", command, err))?;
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let status = match output.status.code() {
+            Some(code) => format!("exit status {}", code),
+            None => "killed by a signal".to_string(),
+        };
+        return Err(match stderr.trim() {
+            "" => format!("'{}' failed ({})", command, status),
+            stderr => format!("'{}' failed ({}): {}", command, status, stderr),
+        });
+    }
+    match serde_json::from_slice::<Value>(&output.stdout) {
+        Ok(Value::Object(map)) => Ok(map.into_iter().collect()),
+        _ => Ok(BTreeMap::new()),
+    }
+}
@@ -0,0 +1,182 @@
+//! Shared request fields: a top-level `defaults` every request inherits, and named `fragments`
+//! that requests pick with `extends`.
+
+use serde_json::{Map, Value};
+
+/// Fields whose objects are merged key by key instead of replaced; a `null` entry removes an inherited key.
+const MERGED_FIELDS: &[&str] = &["headers", "params"];
+
+/// Adds the fields of `base` that `request` does not set itself.
+fn inherit(request: &mut Map<String, Value>, base: &Map<String, Value>) {
+    for (key, inherited) in base {
+        match (request.get_mut(key), inherited) {
+            (None, _) => {
+                request.insert(key.clone(), inherited.clone());
+            }
+            (Some(Value::Object(own)), Value::Object(inherited)) if MERGED_FIELDS.contains(&key.as_str()) => {
+                for (name, value) in inherited {
+                    own.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn object(value: Value, what: &str) -> Result<Map<String, Value>, String> {
+    match value {
+        Value::Object(map) => Ok(map),
+        other => Err(format!("{} must be an object, found {}", what, other)),
+    }
+}
+
+fn fragment_names(extends: Value, request: &str) -> Result<Vec<String>, String> {
+    let invalid = || format!("'extends' of request '{}' must be a fragment name or a list of them", request);
+    match extends {
+        Value::String(name) => Ok(vec![name]),
+        Value::Array(names) => names.into_iter()
+            .map(|name| match name {
+                Value::String(name) => Ok(name),
+                _ => Err(invalid()),
+            })
+            .collect(),
+        _ => Err(invalid()),
+    }
+}
+
+/// Merges `defaults` and the `fragments` named by each request's `extends` into the requests of
+/// `suite` (a request file), in that order, each layer overriding the one before and the request
+/// overriding them all. Returns whether the file uses either.
+pub fn resolve(suite: &mut Value) -> Result<bool, String> {
+    let (defaults, fragments) = match suite.as_object_mut() {
+        Some(map) => (map.remove("defaults"), map.remove("fragments")),
+        None => (None, None),
+    };
+    let defaults = defaults.map(|defaults| object(defaults, "'defaults'")).transpose()?;
+    let fragments = match fragments {
+        Some(fragments) => object(fragments, "'fragments'")?
+            .into_iter()
+            .map(|(name, fragment)| {
+                let fragment = object(fragment, &format!("Fragment '{}'", name))?;
+                if fragment.contains_key("extends") {
+                    return Err(format!("Fragment '{}' cannot use 'extends'", name));
+                }
+                Ok((name, Value::Object(fragment)))
+            })
+            .collect::<Result<Map<String, Value>, String>>()?,
+        None => Map::new(),
+    };
+
+    let requests = match suite {
+        Value::Array(requests) => requests,
+        Value::Object(map) => match map.get_mut("requests") {
+            Some(Value::Array(requests)) => requests,
+            _ => return Ok(defaults.is_some()),
+        },
+        _ => return Ok(false),
+    };
+    let mut used = defaults.is_some();
+    for request in requests.iter_mut() {
+        let Value::Object(request) = request else { continue };
+        let extends = request.remove("extends");
+        if extends.is_none() && defaults.is_none() {
+            continue;
+        }
+        used = true;
+        let name = request.get("name").and_then(Value::as_str).unwrap_or("Unnamed").to_string();
+
+        let mut base = defaults.clone().unwrap_or_default();
+        for fragment_name in extends.map(|extends| fragment_names(extends, &name)).transpose()?.unwrap_or_default() {
+            let Some(Value::Object(fragment)) = fragments.get(&fragment_name) else {
+                let available: Vec<&str> = fragments.keys().map(String::as_str).collect();
+                return Err(if available.is_empty() {
+                    format!("Request '{}' extends '{}', but the file has no 'fragments'", name, fragment_name)
+                } else {
+                    format!("Request '{}' extends unknown fragment '{}' (available: {})", name, fragment_name, available.join(", "))
+                });
+            };
+            let mut layer = fragment.clone();
+            inherit(&mut layer, &base);
+            base = layer;
+        }
+        inherit(request, &base);
+
+        for field in MERGED_FIELDS {
+            if let Some(Value::Object(map)) = request.get_mut(*field) {
+                map.retain(|_, value| !value.is_null());
+            }
+        }
+    }
+    Ok(used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn layers_apply_in_order_and_headers_merge_by_name() {
+        let mut suite = json!({
+            "defaults": { "method": "GET", "timeout_ms": 1000, "headers": { "A": "defaults", "B": "defaults", "C": "defaults" } },
+            "fragments": {
+                "first": { "method": "POST", "headers": { "B": "first" }, "params": { "p": "1", "q": "1" } },
+                "second": { "timeout_ms": 2000, "headers": { "B": "second" } }
+            },
+            "requests": [
+                { "name": "r", "url": "http://localhost/", "extends": ["first", "second"], "headers": { "C": "own", "A": null }, "params": { "q": "2" } }
+            ]
+        });
+        assert_eq!(resolve(&mut suite), Ok(true));
+        assert_eq!(suite, json!({
+            "requests": [{
+                "name": "r",
+                "url": "http://localhost/",
+                "method": "POST",
+                "timeout_ms": 2000,
+                "headers": { "B": "second", "C": "own" },
+                "params": { "p": "1", "q": "2" }
+            }]
+        }));
+    }
+
+    #[test]
+    fn other_fields_are_replaced_as_a_whole() {
+        let mut suite = json!({
+            "defaults": { "body": { "a": 1, "b": 2 }, "tags": ["x"] },
+            "requests": [{ "url": "http://localhost/", "body": { "a": 3 } }]
+        });
+        resolve(&mut suite).unwrap();
+        assert_eq!(suite["requests"][0]["body"], json!({ "a": 3 }));
+        assert_eq!(suite["requests"][0]["tags"], json!(["x"]));
+    }
+
+    #[test]
+    fn file_without_defaults_or_extends_is_left_alone() {
+        let mut suite = json!([{ "url": "http://localhost/", "headers": { "A": null } }]);
+        let original = suite.clone();
+        assert_eq!(resolve(&mut suite), Ok(false));
+        assert_eq!(suite, original);
+    }
+
+    #[test]
+    fn unknown_fragment_is_an_error() {
+        let mut suite = json!({
+            "fragments": { "admin": {}, "write": {} },
+            "requests": [{ "name": "r", "url": "http://localhost/", "extends": "nope" }]
+        });
+        assert_eq!(resolve(&mut suite), Err("Request 'r' extends unknown fragment 'nope' (available: admin, write)".to_string()));
+
+        let mut suite = json!([{ "name": "r", "url": "http://localhost/", "extends": "nope" }]);
+        assert_eq!(resolve(&mut suite), Err("Request 'r' extends 'nope', but the file has no 'fragments'".to_string()));
+    }
+
+    #[test]
+    fn fragment_cannot_extend_another() {
+        let mut suite = json!({
+            "fragments": { "base": {}, "admin": { "extends": "base" } },
+            "requests": [{ "url": "http://localhost/" }]
+        });
+        assert_eq!(resolve(&mut suite), Err("Fragment 'admin' cannot use 'extends'".to_string()));
+    }
+}
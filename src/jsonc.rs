@@ -0,0 +1,68 @@
+//! Relaxed request files (JSONC): `//` and `/* */` comments and trailing commas.
+
+/// `text` as plain JSON: comments and trailing commas are replaced by spaces, keeping every other
+/// character (and so the line and column of JSON errors) where it was.
+pub fn strip(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    let mut in_string = false;
+    // Byte position in `out` of the last comma outside a string, while only whitespace and comments follow it.
+    let mut pending_comma: Option<usize> = None;
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            out.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        out.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '/' if chars.peek() == Some(&'/') => {
+                out.push(' ');
+                while let Some(&next) = chars.peek() {
+                    if next == '\n' {
+                        break;
+                    }
+                    out.push(' ');
+                    chars.next();
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                out.push_str("  ");
+                let mut previous = ' ';
+                for next in chars.by_ref() {
+                    out.push(if next == '\n' { '\n' } else { ' ' });
+                    if previous == '*' && next == '/' {
+                        break;
+                    }
+                    previous = next;
+                }
+            }
+            ']' | '}' => {
+                if let Some(position) = pending_comma.take() {
+                    out.replace_range(position..position + 1, " ");
+                }
+                out.push(c);
+            }
+            ',' => {
+                pending_comma = Some(out.len());
+                out.push(c);
+            }
+            c if c.is_whitespace() => out.push(c),
+            _ => {
+                pending_comma = None;
+                in_string = c == '"';
+                out.push(c);
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,158 @@
+//! `--load`: sends the requests round-robin at a fixed rate for a fixed time, then aggregates the results.
+
+use crate::{report, ConnectionUse, RequestOptions, RequestResult, RequestSpec};
+use futures::stream::{FuturesUnordered, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[derive(Debug, Serialize, Default)]
+pub struct Latency {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+}
+
+impl Latency {
+    fn of(results: &[&RequestResult]) -> Latency {
+        let mut times: Vec<f64> = results.iter().map(|result| result.response_time_ms).collect();
+        if times.is_empty() {
+            return Latency::default();
+        }
+        times.sort_by(f64::total_cmp);
+        // Nearest-rank percentile.
+        let percentile = |p: f64| times[((p / 100.0 * times.len() as f64).ceil() as usize).clamp(1, times.len()) - 1];
+        Latency {
+            min_ms: times[0],
+            mean_ms: times.iter().sum::<f64>() / times.len() as f64,
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p95_ms: percentile(95.0),
+            p99_ms: percentile(99.0),
+            max_ms: times[times.len() - 1],
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct RequestLoad {
+    pub name: String,
+    pub method: String,
+    pub total: usize,
+    pub failed: usize,
+    pub latency: Latency,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LoadReport {
+    pub target_rps: f64,
+    pub duration_s: f64,
+    /// From the first request until the last response came back.
+    pub elapsed_s: f64,
+    pub total: usize,
+    pub success: usize,
+    pub failed: usize,
+    pub error_rate: f64,
+    pub throughput_rps: f64,
+    pub latency: Latency,
+    /// How many connections were opened; the other requests reused one.
+    pub connections_opened: usize,
+    pub requests: Vec<RequestLoad>,
+    /// How often each failure reason occurred.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub errors: BTreeMap<String, usize>,
+}
+
+/// The time between two requests at `rps` requests per second, if it is representable: neither zero
+/// (more than one request per nanosecond) nor beyond what a `Duration` holds.
+pub fn period(rps: f64) -> Result<Duration, String> {
+    if !(rps > 0.0 && rps.is_finite()) {
+        return Err("--rps must be a positive number".to_string());
+    }
+    match Duration::try_from_secs_f64(1.0 / rps) {
+        Ok(period) if !period.is_zero() => Ok(period),
+        Ok(_) => Err(format!("--rps {} is too high; at most one request per nanosecond can be started", rps)),
+        Err(_) => Err(format!("--rps {} is too low", rps)),
+    }
+}
+
+/// Starts one request every `period` (see `period`), cycling through `requests`, until `duration` has
+/// passed; requests still in flight then are waited for. Requests are not held back by slow
+/// responses, so the rate stays fixed however the server copes.
+pub async fn run(
+    client: &reqwest::Client,
+    requests: &[RequestSpec],
+    options: Arc<RequestOptions>,
+    rps: f64,
+    period: Duration,
+    duration: Duration,
+) -> LoadReport {
+    let start = tokio::time::Instant::now();
+    let mut ticker = tokio::time::interval(period);
+    let stop = tokio::time::sleep(duration);
+    tokio::pin!(stop);
+
+    let mut sending = !requests.is_empty();
+    let mut sent = 0;
+    let mut in_flight = FuturesUnordered::new();
+    let mut results: Vec<(usize, RequestResult)> = Vec::new();
+    loop {
+        tokio::select! {
+            _ = &mut stop, if sending => sending = false,
+            _ = ticker.tick(), if sending => {
+                let index = sent % requests.len();
+                sent += 1;
+                let request = tokio::spawn(crate::process_request(client.clone(), requests[index].clone(), options.clone()));
+                in_flight.push(async move { (index, request.await.expect("request task panicked")) });
+            }
+            Some(done) = in_flight.next() => results.push(done),
+            else => break,
+        }
+    }
+    let elapsed = start.elapsed().as_secs_f64();
+
+    let all: Vec<&RequestResult> = results.iter().map(|(_, result)| result).collect();
+    let failed = all.iter().filter(|result| !result.success).count();
+    let mut errors = BTreeMap::new();
+    for result in all.iter().filter(|result| !result.success) {
+        *errors.entry(report::failure_message(result)).or_insert(0) += 1;
+    }
+    let per_request = requests.iter()
+        .enumerate()
+        .map(|(index, req)| {
+            let of_request: Vec<&RequestResult> = results.iter()
+                .filter(|(position, _)| *position == index)
+                .map(|(_, result)| result)
+                .collect();
+            RequestLoad {
+                name: req.name.as_deref().unwrap_or("Unnamed").to_string(),
+                method: req.method.to_uppercase(),
+                total: of_request.len(),
+                failed: of_request.iter().filter(|result| !result.success).count(),
+                latency: Latency::of(&of_request),
+            }
+        })
+        .collect();
+
+    LoadReport {
+        target_rps: rps,
+        duration_s: duration.as_secs_f64(),
+        elapsed_s: elapsed,
+        total: all.len(),
+        success: all.len() - failed,
+        failed,
+        error_rate: if all.is_empty() { 0.0 } else { failed as f64 / all.len() as f64 * 100.0 },
+        throughput_rps: if elapsed > 0.0 { all.len() as f64 / elapsed } else { 0.0 },
+        latency: Latency::of(&all),
+        connections_opened: all.iter()
+            .filter(|result| result.timing.as_ref().and_then(|timing| timing.connection) == Some(ConnectionUse::New))
+            .count(),
+        requests: per_request,
+        errors,
+    }
+}
@@ -4,44 +4,563 @@ use std::fs;
 use std::time::Duration;
 use reqwest::header::{HeaderMap, HeaderValue, HeaderName};
 use std::str::FromStr;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tokio::sync::Semaphore;
 use colored::*;
 use regex::Regex;
 use std::env;
-use dotenvy;
+use std::io::{self, BufRead, IsTerminal, Write};
 use once_cell::sync::Lazy;
 use futures::stream::{FuturesUnordered, StreamExt};
 
+mod archive;
+mod assertions;
+mod auth;
+mod data;
+mod diff;
+mod drift;
+mod expr;
+mod highlight;
+mod hook;
+mod inherit;
+mod jsonc;
+mod load;
+mod record;
+mod redact;
+mod report;
+mod select;
+mod snapshot;
+mod template;
+mod tui;
+
 static ENV_VAR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{(\w+)\}\}").unwrap());
 
 fn substitute_env_vars(text: &str) -> String {
-    ENV_VAR_REGEX.replace_all(text, |caps: &regex::Captures| {
-        let var_name = &caps[1];
-        env::var(var_name).unwrap_or_else(|_| caps[0].to_string())
+    substitute_variables(text, &HashMap::new())
+}
+
+/// A `run_if` or `skip_if` field in the text of a request file; the string's content is group 1.
+static CONDITION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r#""(?:run_if|skip_if)"\s*:\s*"((?:[^"\\]|\\.)*)""#).unwrap());
+
+/// Like `substitute_env_vars`, but `variables` (from an `--env` environment) take precedence over the environment.
+fn substitute_variables(text: &str, variables: &HashMap<String, String>) -> String {
+    let lookup = |var_name: &str| variables.get(var_name).cloned().or_else(|| env::var(var_name).ok());
+    // In conditions a variable becomes a string literal, so `{{TOKEN}} != ""` compares its value.
+    let text = CONDITION_REGEX.replace_all(text, |caps: &regex::Captures| {
+        let condition = caps.get(1).unwrap();
+        let quoted = ENV_VAR_REGEX.replace_all(condition.as_str(), |caps: &regex::Captures| match lookup(&caps[1]) {
+            Some(value) => {
+                let literal = serde_json::Value::String(value).to_string();
+                let escaped = serde_json::Value::String(literal).to_string();
+                escaped[1..escaped.len() - 1].to_string()
+            }
+            None => caps[0].to_string(),
+        });
+        let start = condition.start() - caps.get(0).unwrap().start();
+        format!("{}{}\"", &caps[0][..start], quoted)
+    });
+    ENV_VAR_REGEX.replace_all(&text, |caps: &regex::Captures| {
+        lookup(&caps[1]).unwrap_or_else(|| caps[0].to_string())
     }).to_string()
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about = "JSON-based HTTP Request CLI")]
 struct Args {
-    #[arg(short, long)]
-    file: String,
+    /// Request file to run, a glob of files, or "-" to read it from stdin (repeatable)
+    #[arg(short, long, required = true)]
+    file: Vec<String>,
+
+    /// Run a recording HTTP proxy and write the requests it sees to --file instead of running it
+    #[arg(long)]
+    record: bool,
+
+    /// Address the recording proxy listens on
+    #[arg(long, default_value = "127.0.0.1:8888", requires = "record")]
+    listen: String,
     
     #[arg(short, long, default_value = "30")]
     timeout: u64,
+
+    /// Allow comments and trailing commas in every request file (always allowed in .jsonc files)
+    #[arg(long)]
+    relaxed_json: bool,
+
+    /// Use the variables of this entry of the file's `environments` for {{VAR}} placeholders
+    #[arg(long)]
+    env: Option<String>,
+
+    /// Give up on a connection that is not established within this long (e.g. 2s, 500ms), however long --timeout is
+    #[arg(long, value_parser = parse_duration)]
+    connect_timeout: Option<Duration>,
     
-    #[arg(short, long, value_parser = ["pretty", "json"])]
+    #[arg(short, long, value_parser = ["pretty", "json", "junit", "allure", "prometheus"])]
     output: Option<String>,
+
+    /// Directory that receives the result files of `--output allure`
+    #[arg(long, default_value = "allure-results")]
+    allure_dir: String,
+
+    /// Key naming in JSON output
+    #[arg(long, default_value = "snake", value_parser = ["snake", "camel"])]
+    output_case: String,
+
+    /// Ask for confirmation before sending any DELETE request
+    #[arg(long)]
+    confirm_destructive: bool,
+
+    /// Answer "yes" to every confirmation prompt (required when stdin is not a TTY)
+    #[arg(short, long)]
+    yes: bool,
+
+    /// Run the `before` and `after` shell commands of requests (needs --sequential)
+    #[arg(long)]
+    allow_hooks: bool,
+
+    /// Maximum number of requests in flight at once (default: unlimited)
+    #[arg(short, long)]
+    concurrency: Option<usize>,
+
+    /// Send requests one at a time, in file order
+    #[arg(short, long)]
+    sequential: bool,
+
+    /// Print each result only once all requests before it in the file have been printed
+    #[arg(long)]
+    ordered_output: bool,
+
+    /// Pause between sequential requests, or stagger concurrent ones, by this many milliseconds
+    #[arg(long, default_value = "0")]
+    delay_ms: u64,
+
+    /// Stop sending requests after the first assertion failure (HTTP/network errors do not stop the run)
+    #[arg(long)]
+    bail_on_assert: bool,
+
+    /// Stop the run once this many requests have failed; requests in flight are cancelled
+    #[arg(long)]
+    max_failures: Option<usize>,
+
+    /// Stop the run after this long (e.g. 90, 90s, 5m); unfinished requests are reported as not completed
+    #[arg(long, value_parser = parse_duration)]
+    max_duration: Option<Duration>,
+
+    /// Compare every response body with its snapshot in --snapshot-dir, storing it on the first run
+    #[arg(long)]
+    snapshots: bool,
+
+    /// Replace snapshots that differ from the response instead of failing (implies --snapshots)
+    #[arg(long)]
+    update_snapshots: bool,
+
+    /// Directory holding the snapshots, one `<name>.json` per request
+    #[arg(long, default_value = "snapshots")]
+    snapshot_dir: String,
+
+    /// Show only the part of each response body matching this jq-style path (e.g. '.data.items[0].id')
+    #[arg(long, value_parser = select::parse)]
+    select: Option<select::Path>,
+
+    /// Print only the raw response body of the request with this name (or what --select picks from it), for use in scripts
+    #[arg(long, conflicts_with_all = ["output", "watch", "record", "dry_run", "load", "verbose"])]
+    extract: Option<String>,
+
+    /// After the run, browse the results in a terminal view: select a request to see its headers and body
+    #[arg(long, conflicts_with_all = ["output", "watch", "record", "dry_run", "load", "extract", "quiet"])]
+    interactive: bool,
+
+    /// Load-test: send the requests round-robin at --rps for --duration and report throughput and latency
+    #[arg(long, conflicts_with_all = ["watch", "record", "sequential", "concurrency", "snapshots", "update_snapshots", "hash_state", "max_failures"])]
+    load: bool,
+
+    /// Requests per second to send in --load mode
+    #[arg(long, default_value = "10", requires = "load")]
+    rps: f64,
+
+    /// How long --load mode keeps sending (e.g. 30, 30s, 2m)
+    #[arg(long, default_value = "10s", value_parser = parse_duration, requires = "load")]
+    duration: Duration,
+
+    /// Mask secrets in the output: sensitive headers, values of secret environment variables, and secret-looking JSON fields
+    #[arg(long)]
+    redact: bool,
+
+    /// Also mask headers whose name matches this regex (repeatable; requires --redact)
+    #[arg(long, requires = "redact")]
+    redact_header: Vec<String>,
+
+    /// Disable colored output (the NO_COLOR environment variable does the same)
+    #[arg(long)]
+    no_color: bool,
+
+    /// Print only the final summary, without a block per request
+    #[arg(short, long)]
+    quiet: bool,
+
+    /// Print the full request and response of every exchange (-vv also shows Authorization headers)
+    #[arg(short, long, action = clap::ArgAction::Count, conflicts_with = "quiet")]
+    verbose: u8,
+
+    /// Re-run the whole file every time it changes, until Ctrl-C
+    #[arg(short, long, conflicts_with_all = ["record", "dry_run"])]
+    watch: bool,
+
+    /// Print each fully resolved request instead of sending it
+    #[arg(long)]
+    dry_run: bool,
+
+    /// Refuse to run when more than this many requests target a single host
+    #[arg(long)]
+    max_per_host: Option<usize>,
+
+    /// Run only the request with this name, or this 1-based position (repeatable)
+    #[arg(long)]
+    only: Vec<String>,
+
+    /// Run only requests whose name contains this text (case-insensitive)
+    #[arg(long)]
+    filter: Option<String>,
+
+    /// Run only requests carrying this tag (repeatable; any listed tag matches)
+    #[arg(long)]
+    tag: Vec<String>,
+
+    /// Track response body hashes in this state file and report changes since the last run
+    #[arg(long)]
+    hash_state: Option<String>,
+
+    /// Redirect policy: "follow" (up to 10), "none", or a maximum number of redirects
+    #[arg(long, default_value = "follow", value_parser = parse_redirects)]
+    redirects: RedirectMode,
+
+    /// Open a new connection for every request instead of keeping connections alive between them
+    #[arg(long)]
+    no_keepalive: bool,
+
+    /// User-Agent sent with every request that does not set its own
+    #[arg(long)]
+    user_agent: Option<String>,
+
+    /// JSON file of headers sent with every request; a request's own header of the same name wins
+    #[arg(long)]
+    default_headers: Option<String>,
+
+    /// Keep at most this many bytes of each response body; a `save_to` file still gets the whole body
+    #[arg(long)]
+    max_response_bytes: Option<usize>,
+
+    /// Accept header sent with every request that does not set its own (e.g. "application/json")
+    #[arg(long, value_parser = parse_accept)]
+    accept: Option<String>,
+
+    /// Accept-Language header sent with every request that does not set its own (e.g. "en-US, ko;q=0.8")
+    #[arg(long, value_parser = parse_accept_language)]
+    accept_language: Option<String>,
+
+    /// Send every request through this proxy (overrides HTTP_PROXY/HTTPS_PROXY)
+    #[arg(long)]
+    proxy: Option<String>,
+
+    /// Send a generated UUID in this header on every request (e.g. X-Request-Id)
+    #[arg(long)]
+    trace_header: Option<String>,
+
+    /// HTTP version to use: "auto" (negotiated), "http1" (HTTP/1.1 only), or "http2" (prior knowledge)
+    #[arg(long, default_value = "auto", value_parser = ["auto", "http1", "http2"])]
+    http_version: String,
+
+    /// Accept invalid TLS certificates (self-signed, expired, wrong host)
+    #[arg(short = 'k', long)]
+    insecure: bool,
+
+    /// PEM client certificate for mutual TLS (requires --client-key)
+    #[arg(long, requires = "client_key", conflicts_with = "identity")]
+    client_cert: Option<String>,
+
+    /// PEM (PKCS#8) private key for --client-cert
+    #[arg(long, requires = "client_cert")]
+    client_key: Option<String>,
+
+    /// PKCS#12 identity (certificate and key) for mutual TLS
+    #[arg(long)]
+    identity: Option<String>,
+
+    /// Password for the --identity file
+    #[arg(long, requires = "identity", default_value = "")]
+    identity_password: String,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum RedirectMode {
+    None,
+    Limited(usize),
+}
+
+fn parse_redirects(value: &str) -> Result<RedirectMode, String> {
+    match value {
+        "follow" => Ok(RedirectMode::Limited(10)),
+        "none" => Ok(RedirectMode::None),
+        other => other.parse::<usize>()
+            .map(|max| if max == 0 { RedirectMode::None } else { RedirectMode::Limited(max) })
+            .map_err(|_| format!("expected 'follow', 'none', or a number, got '{}'", other)),
+    }
+}
+
+/// A plain number of seconds, or a duration with units such as "500ms", "90s", or "5m".
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(Duration::from_secs(seconds));
+    }
+    humantime::parse_duration(value).map_err(|err| format!("invalid duration '{}': {}", value, err))
+}
+
+static MEDIA_RANGE_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[\w.+*-]+/[\w.+*-]+$").unwrap());
+static LANGUAGE_RANGE_REGEX: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"^(\*|[A-Za-z]{1,8}(-[A-Za-z0-9]{1,8})*)$").unwrap());
+
+/// Checks a comma-separated negotiation header value: every item must match `item` and may carry
+/// parameters, of which a `q` weight must be a number from 0 to 1.
+fn parse_negotiation(value: &str, item: &Regex, expected: &str) -> Result<String, String> {
+    for part in value.split(',') {
+        let mut pieces = part.split(';').map(str::trim);
+        let range = pieces.next().unwrap_or("");
+        if !item.is_match(range) {
+            return Err(format!("expected {}, got '{}'", expected, range));
+        }
+        for param in pieces {
+            let Some((key, weight)) = param.split_once('=') else {
+                return Err(format!("invalid parameter '{}' of '{}'", param, range));
+            };
+            if key.trim().eq_ignore_ascii_case("q")
+                && !weight.trim().parse::<f64>().is_ok_and(|q| (0.0..=1.0).contains(&q))
+            {
+                return Err(format!("q of '{}' must be a number from 0 to 1, got '{}'", range, weight.trim()));
+            }
+        }
+    }
+    Ok(value.to_string())
+}
+
+fn parse_accept(value: &str) -> Result<String, String> {
+    parse_negotiation(value, &MEDIA_RANGE_REGEX, "a media type such as 'application/json' or '*/*'")
+}
+
+fn parse_accept_language(value: &str) -> Result<String, String> {
+    parse_negotiation(value, &LANGUAGE_RANGE_REGEX, "a language tag such as 'en', 'en-US', or '*'")
+}
+
+impl RedirectMode {
+    fn policy(self) -> reqwest::redirect::Policy {
+        match self {
+            RedirectMode::None => reqwest::redirect::Policy::none(),
+            RedirectMode::Limited(max) => reqwest::redirect::Policy::limited(max),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 struct RequestSpec {
     name: Option<String>,
     url: String,
+    #[serde(default)]
     method: String,
-    headers: Option<std::collections::HashMap<String, String>>,
-    params: Option<std::collections::HashMap<String, String>>,
+    headers: Option<std::collections::HashMap<String, OneOrMany>>,
+    params: Option<std::collections::HashMap<String, OneOrMany>>,
     body: Option<serde_json::Value>,
-    form: Option<std::collections::HashMap<String, String>>,
+    form: Option<std::collections::HashMap<String, OneOrMany>>,
+    graphql: Option<GraphQl>,
+    body_base: Option<serde_json::Value>,
+    body_patch: Option<serde_json::Value>,
+    body_dir: Option<String>,
+    body_glob: Option<String>,
+    auth: Option<auth::Auth>,
+    tags: Option<Vec<String>>,
+    group: Option<String>,
+    max_concurrency: Option<usize>,
+    timeout_ms: Option<u64>,
+    /// Fail the request when its response takes longer than this, whatever the status.
+    max_response_ms: Option<f64>,
+    delay_before_ms: Option<u64>,
+    /// Send the request again up to this many times after a network error, timeout, or 5xx status.
+    /// Only idempotent methods are retried, unless `retry_non_idempotent` is set.
+    retry: Option<u32>,
+    retry_non_idempotent: Option<bool>,
+    #[serde(rename = "assert")]
+    assertions: Option<assertions::Assertions>,
+    schema: Option<serde_json::Value>,
+    ignore_paths: Option<Vec<String>>,
+    capture: Option<HashMap<String, String>>,
+    save_to: Option<String>,
+    binary: Option<bool>,
+    run_if: Option<String>,
+    skip_if: Option<String>,
+    depends_on: Option<Vec<String>>,
+    /// CSV or JSON file whose rows this request is repeated for; only read while loading the file.
+    data: Option<String>,
+    confirm: Option<bool>,
+    /// Shell commands run just before sending the request and after its response; need --allow-hooks.
+    before: Option<String>,
+    after: Option<String>,
+    /// The file this request was loaded from, when several files are run together.
+    #[serde(skip)]
+    source: Option<String>,
+    /// Where this request is among the requests of the run, from 1.
+    #[serde(skip)]
+    position: usize,
+}
+
+/// A request file: either a bare array of requests, or an object that also carries
+/// `invariants` checked once every request has finished (and `environments`, see `environment_variables`,
+/// and `defaults` and `fragments`, see `inherit`).
+#[derive(Debug, Deserialize)]
+struct SuiteFile {
+    requests: Vec<RequestSpec>,
+    #[serde(default)]
+    invariants: Vec<Invariant>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct Invariant {
+    name: Option<String>,
+    expr: String,
+}
+
+/// A JSON error followed by the line it points at, with a caret under the column.
+fn json_error(text: &str, err: &serde_json::Error) -> String {
+    let Some(line) = text.lines().nth(err.line().saturating_sub(1)) else {
+        return err.to_string();
+    };
+    let number = err.line().to_string();
+    let caret_at = line.chars().take(err.column().saturating_sub(1)).count();
+    format!(
+        "{}\n  {} | {}\n  {} | {}^",
+        err, number, line, " ".repeat(number.len()), " ".repeat(caret_at)
+    )
+}
+
+fn parse_suite(text: &str) -> Result<SuiteFile, String> {
+    if text.trim().is_empty() {
+        return Err("the file is empty; expected a JSON array of requests or an object with \"requests\"".to_string());
+    }
+    let mut value: serde_json::Value = serde_json::from_str(text).map_err(|err| json_error(text, &err))?;
+    let inherited = inherit::resolve(&mut value)?;
+    let is_array = value.is_array();
+    let suite = match (inherited, is_array) {
+        // Merged requests no longer match the text, so their errors cannot point at a line.
+        (true, true) => SuiteFile { requests: serde_json::from_value(value.clone()).map_err(|err| err.to_string())?, invariants: Vec::new() },
+        (true, false) => serde_json::from_value(value.clone()).map_err(|err| err.to_string())?,
+        (false, true) => SuiteFile { requests: serde_json::from_str(text).map_err(|err| json_error(text, &err))?, invariants: Vec::new() },
+        (false, false) => serde_json::from_str(text).map_err(|err| json_error(text, &err))?,
+    };
+    if suite.requests.iter().all(|req| req.data.is_none()) {
+        return Ok(suite);
+    }
+
+    // The data-driven requests are repeated on the parsed value, which already has their inherited fields.
+    let serde_json::Value::Array(requests) = (if is_array { value } else { value["requests"].take() }) else {
+        unreachable!("'requests' was parsed as an array above");
+    };
+    let requests = data::expand(requests)?
+        .into_iter()
+        .map(|request| {
+            let name = request.get("name").and_then(serde_json::Value::as_str).unwrap_or("Unnamed").to_string();
+            serde_json::from_value(request).map_err(|err| format!("request '{}': {}", name, err))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(SuiteFile { requests, ..suite })
+}
+
+#[derive(Debug, Deserialize, Clone)]
+struct GraphQl {
+    query: String,
+    variables: Option<serde_json::Value>,
+    operation_name: Option<String>,
+}
+
+impl GraphQl {
+    fn to_body(&self) -> serde_json::Value {
+        let mut body = serde_json::json!({ "query": self.query });
+        if let Some(variables) = &self.variables {
+            body["variables"] = variables.clone();
+        }
+        if let Some(operation_name) = &self.operation_name {
+            body["operationName"] = serde_json::json!(operation_name);
+        }
+        body
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize, Clone)]
+#[serde(untagged)]
+enum OneOrMany {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl OneOrMany {
+    fn values(&self) -> &[String] {
+        match self {
+            OneOrMany::One(value) => std::slice::from_ref(value),
+            OneOrMany::Many(values) => values,
+        }
+    }
+
+    fn expand(&self) -> Result<OneOrMany, String> {
+        Ok(match self {
+            OneOrMany::One(value) => OneOrMany::One(template::expand(value)?),
+            OneOrMany::Many(values) => OneOrMany::Many(values.iter().map(|value| template::expand(value)).collect::<Result<_, _>>()?),
+        })
+    }
+}
+
+/// A copy of `req` with the `{{$function}}` placeholders in what it sends expanded.
+fn expand_templates(req: &RequestSpec) -> Result<RequestSpec, String> {
+    let expand_map = |map: &HashMap<String, OneOrMany>| -> Result<HashMap<String, OneOrMany>, String> {
+        map.iter().map(|(key, value)| Ok((key.clone(), value.expand()?))).collect()
+    };
+    let expand_value = |value: &Option<serde_json::Value>| value.as_ref().map(template::expand_value).transpose();
+
+    let mut expanded = req.clone();
+    expanded.url = template::expand(&req.url)?;
+    expanded.headers = req.headers.as_ref().map(expand_map).transpose()?;
+    expanded.params = req.params.as_ref().map(expand_map).transpose()?;
+    expanded.body = expand_value(&req.body)?;
+    expanded.body_base = expand_value(&req.body_base)?;
+    expanded.body_patch = expand_value(&req.body_patch)?;
+    expanded.form = req.form.as_ref().map(expand_map).transpose()?;
+    if let Some(graphql) = &mut expanded.graphql {
+        graphql.query = template::expand(&graphql.query)?;
+        graphql.variables = expand_value(&graphql.variables)?;
+    }
+    Ok(expanded)
+}
+
+fn to_pairs(map: &HashMap<String, OneOrMany>) -> Vec<(String, String)> {
+    map.iter()
+        .flat_map(|(key, value)| value.values().iter().map(move |value| (key.clone(), value.clone())))
+        .collect()
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ErrorKind {
+    /// The request definition itself is invalid.
+    Spec,
+    Timeout,
+    /// The host name could not be resolved.
+    Dns,
+    /// No connection could be established (e.g. it was refused).
+    Connect,
+    /// The connection was established, but the TLS handshake failed.
+    Tls,
+    Network,
+    /// The server answered with a non-2xx status.
+    Status,
+    /// The response came back but violated an assertion.
+    Assertion,
+    /// A `before` or `after` hook command failed.
+    Hook,
 }
 
 #[derive(Debug, Serialize, Clone)]
@@ -55,6 +574,139 @@ struct RequestResult {
     response_time_ms: f64,
     response_body: Option<serde_json::Value>,
     error: Option<String>,
+    error_kind: Option<ErrorKind>,
+    skipped: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    /// The protocol version of the response, e.g. "HTTP/2.0".
+    http_version: Option<String>,
+    trace_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    assertions: Vec<assertions::AssertionOutcome>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    archive: Option<archive::ArchiveReport>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    captures: BTreeMap<String, serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    saved_to: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    snapshot: Option<snapshot::SnapshotResult>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    timing: Option<Timing>,
+    /// Size of a binary response body, which is neither parsed nor printed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    binary_size: Option<usize>,
+    /// The Content-Type header of the response.
+    content_type: Option<String>,
+    /// Length of the (decompressed) response body as read, before any parsing.
+    body_bytes: usize,
+    /// The body was longer than --max-response-bytes, so only its beginning was kept.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    truncated: bool,
+    /// How many times the request was sent, when it had to be retried.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attempts: Option<u32>,
+    /// The body as received, kept only for the request named by --extract.
+    #[serde(skip)]
+    raw_body: Option<Vec<u8>>,
+    #[serde(skip)]
+    position: usize,
+    #[serde(skip)]
+    started_at: Option<std::time::SystemTime>,
+    #[serde(skip)]
+    request_body: Option<serde_json::Value>,
+    #[serde(skip)]
+    exchange: Option<Exchange>,
+}
+
+/// Where the time of a request went. `response_time_ms` covers the same span as `headers_ms`.
+#[derive(Debug, Serialize, Clone)]
+struct Timing {
+    /// From sending the request until the response headers arrived (connecting included).
+    headers_ms: f64,
+    /// Downloading (and decompressing) the response body.
+    body_ms: f64,
+    total_ms: f64,
+    /// Whether the request went over a newly opened connection or one kept alive from an earlier request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    connection: Option<ConnectionUse>,
+}
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+enum ConnectionUse {
+    New,
+    Reused,
+}
+
+/// The raw request and response, kept for `--verbose`.
+#[derive(Debug, Clone, Default)]
+struct Exchange {
+    request_line: String,
+    request_headers: Vec<(String, String)>,
+    request_body: Option<String>,
+    status_line: Option<String>,
+    response_headers: Vec<(String, String)>,
+    response_text: Option<String>,
+}
+
+fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers.iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+        .collect()
+}
+
+impl RequestResult {
+    fn new(req: &RequestSpec) -> Self {
+        RequestResult {
+            name: req.name.as_deref().unwrap_or("Unnamed").to_string(),
+            url: req.url.clone(),
+            method: req.method.clone(),
+            status_code: None,
+            status_text: None,
+            success: false,
+            response_time_ms: 0.0,
+            response_body: None,
+            error: None,
+            error_kind: None,
+            skipped: false,
+            file: req.source.clone(),
+            http_version: None,
+            trace_id: None,
+            assertions: Vec::new(),
+            archive: None,
+            captures: BTreeMap::new(),
+            saved_to: None,
+            snapshot: None,
+            timing: None,
+            binary_size: None,
+            content_type: None,
+            body_bytes: 0,
+            truncated: false,
+            attempts: None,
+            raw_body: None,
+            position: req.position,
+            started_at: None,
+            request_body: None,
+            exchange: None,
+        }
+    }
+
+    fn failed(req: &RequestSpec, error: impl Into<String>) -> Self {
+        RequestResult {
+            error: Some(error.into()),
+            error_kind: Some(ErrorKind::Spec),
+            ..RequestResult::new(req)
+        }
+    }
+
+    fn skipped(req: &RequestSpec, reason: &str) -> Self {
+        RequestResult {
+            skipped: true,
+            error_kind: None,
+            ..RequestResult::failed(req, reason)
+        }
+    }
 }
 
 #[derive(Debug, Serialize)]
@@ -62,99 +714,637 @@ struct TestSummary {
     total: usize,
     success: usize,
     failed: usize,
+    skipped: usize,
     success_rate: f64,
+    aborted: Option<String>,
+    hosts: Vec<HostSummary>,
+    /// Requests that were sent, by status-code class ("2xx" ... "5xx"); "errors" got no response.
+    status_classes: BTreeMap<String, usize>,
+    methods: Vec<MethodSummary>,
+    slowest: Option<SlowestRequest>,
+    /// Response body bytes received over the whole run.
+    total_bytes: usize,
+    drift: Option<drift::DriftReport>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    invariants: Vec<assertions::AssertionOutcome>,
     results: Vec<RequestResult>,
 }
 
-async fn process_request(client: reqwest::Client, req: RequestSpec, timeout: u64) -> RequestResult {
-    let request_name = req.name.as_deref().unwrap_or("Unnamed").to_string();
+#[derive(Debug, Serialize)]
+struct HostSummary {
+    host: String,
+    total: usize,
+    success: usize,
+    failed: usize,
+    success_rate: f64,
+}
+
+#[derive(Debug, Serialize)]
+struct MethodSummary {
+    method: String,
+    total: usize,
+    success: usize,
+    failed: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct SlowestRequest {
+    name: String,
+    method: String,
+    url: String,
+    response_time_ms: f64,
+}
+
+fn host_of(url: &str) -> String {
+    match reqwest::Url::parse(url) {
+        Ok(parsed) => match (parsed.host_str(), parsed.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => "(no host)".to_string(),
+        },
+        Err(_) => "(invalid url)".to_string(),
+    }
+}
+
+fn summarize_hosts(results: &[RequestResult]) -> Vec<HostSummary> {
+    let mut hosts: std::collections::BTreeMap<String, HostSummary> = std::collections::BTreeMap::new();
+    for result in results {
+        let host = host_of(&result.url);
+        let entry = hosts.entry(host.clone()).or_insert(HostSummary {
+            host,
+            total: 0,
+            success: 0,
+            failed: 0,
+            success_rate: 0.0,
+        });
+        entry.total += 1;
+        if result.success {
+            entry.success += 1;
+        } else if !result.skipped {
+            entry.failed += 1;
+        }
+    }
+
+    hosts.into_values()
+        .map(|mut entry| {
+            let executed = entry.success + entry.failed;
+            if executed > 0 {
+                entry.success_rate = (entry.success as f64 / executed as f64) * 100.0;
+            }
+            entry
+        })
+        .collect()
+}
+
+fn summarize_status_classes(results: &[RequestResult]) -> BTreeMap<String, usize> {
+    let mut classes = BTreeMap::new();
+    for result in results.iter().filter(|result| !result.skipped) {
+        let class = match result.status_code {
+            Some(code) => format!("{}xx", code / 100),
+            None => "errors".to_string(),
+        };
+        *classes.entry(class).or_insert(0) += 1;
+    }
+    classes
+}
+
+fn summarize_methods(results: &[RequestResult]) -> Vec<MethodSummary> {
+    let mut methods: BTreeMap<String, MethodSummary> = BTreeMap::new();
+    for result in results {
+        let entry = methods.entry(result.method.clone()).or_insert(MethodSummary {
+            method: result.method.clone(),
+            total: 0,
+            success: 0,
+            failed: 0,
+        });
+        entry.total += 1;
+        if result.success {
+            entry.success += 1;
+        } else if !result.skipped {
+            entry.failed += 1;
+        }
+    }
+    methods.into_values().collect()
+}
+
+fn slowest_request(results: &[RequestResult]) -> Option<SlowestRequest> {
+    results.iter()
+        .filter(|result| !result.skipped)
+        .max_by(|a, b| a.response_time_ms.total_cmp(&b.response_time_ms))
+        .map(|result| SlowestRequest {
+            name: result.name.clone(),
+            method: result.method.clone(),
+            url: result.url.clone(),
+            response_time_ms: result.response_time_ms,
+        })
+}
+
+fn merge_json(base: &mut serde_json::Value, patch: serde_json::Value) {
+    match (base, patch) {
+        (serde_json::Value::Object(base_map), serde_json::Value::Object(patch_map)) => {
+            for (key, value) in patch_map {
+                match base_map.get_mut(&key) {
+                    Some(existing) => merge_json(existing, value),
+                    None => {
+                        base_map.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, patch) => *base = patch,
+    }
+}
+
+fn effective_body(req: &RequestSpec) -> Result<Option<serde_json::Value>, String> {
+    let Some(base) = &req.body_base else {
+        if req.body_patch.is_some() {
+            return Err("'body_patch' requires 'body_base'.".to_string());
+        }
+        return Ok(req.body.clone());
+    };
+    if req.body.is_some() {
+        return Err("Cannot use 'body' and 'body_base' fields simultaneously.".to_string());
+    }
+
+    let mut body = match base {
+        serde_json::Value::String(path) => {
+            let data = fs::read_to_string(path)
+                .map_err(|err| format!("Failed to read body_base file '{}': {}", path, err))?;
+            serde_json::from_str(&substitute_env_vars(&data))
+                .map_err(|err| format!("Invalid JSON in body_base file '{}': {}", path, err))?
+        }
+        inline => inline.clone(),
+    };
+    if let Some(patch) = &req.body_patch {
+        merge_json(&mut body, patch.clone());
+    }
+    Ok(Some(body))
+}
+
+struct RequestOptions {
+    timeout: u64,
+    connect_timeout: Option<Duration>,
+    trace_header: Option<HeaderName>,
+    keep_exchange: bool,
+    /// Local and remote address of every connection seen so far; a connection is identified by the pair.
+    connections: std::sync::Mutex<std::collections::HashSet<(std::net::SocketAddr, std::net::SocketAddr)>>,
+    /// Headers from --default-headers, added to requests that do not set them.
+    default_headers: HashMap<String, OneOrMany>,
+    /// Where snapshots are kept, when snapshot testing is enabled.
+    snapshot_dir: Option<std::path::PathBuf>,
+    update_snapshots: bool,
+    max_response_bytes: Option<usize>,
+    /// Name of the request whose raw body is kept for --extract.
+    keep_raw_body: Option<String>,
+}
+
+struct PreparedRequest {
+    builder: reqwest::RequestBuilder,
+    trace_id: Option<String>,
+    request_body: Option<serde_json::Value>,
+    archive: Option<Arc<archive::ArchiveStats>>,
+    schema: Option<jsonschema::Validator>,
+    /// Username and password to answer a Digest challenge with.
+    digest: Option<(String, String)>,
+}
+
+fn load_default_headers(path: &str) -> Result<HashMap<String, OneOrMany>, String> {
+    let data = fs::read_to_string(path).map_err(|err| format!("Failed to read --default-headers file '{}': {}", path, err))?;
+    serde_json::from_str(&substitute_env_vars(&data))
+        .map_err(|err| format!("Invalid --default-headers file '{}': {}", path, err))
+}
+
+/// The `--default-headers` file plus `--accept` and `--accept-language`, which replace the file's headers of the same name.
+fn global_headers(args: &Args) -> Result<HashMap<String, OneOrMany>, String> {
+    let mut headers = args.default_headers.as_deref().map(load_default_headers).transpose()?.unwrap_or_default();
+    for (name, value) in [("Accept", &args.accept), ("Accept-Language", &args.accept_language)] {
+        if let Some(value) = value {
+            headers.retain(|existing, _| !existing.eq_ignore_ascii_case(name));
+            headers.insert(name.to_string(), OneOrMany::One(value.clone()));
+        }
+    }
+    Ok(headers)
+}
+
+/// The request's headers plus every default header it does not set itself (names compare case-insensitively).
+fn with_default_headers(req: &RequestSpec, defaults: &HashMap<String, OneOrMany>) -> RequestSpec {
+    let mut req = req.clone();
+    if defaults.is_empty() {
+        return req;
+    }
+    let headers = req.headers.get_or_insert_with(HashMap::new);
+    for (name, value) in defaults {
+        if !headers.keys().any(|own| own.eq_ignore_ascii_case(name)) {
+            headers.insert(name.clone(), value.clone());
+        }
+    }
+    req
+}
 
+/// `req` as it is sent: with the default headers added and its `{{$function}}` placeholders expanded.
+/// Done once per request, so that retries send the same values (e.g. the same `Idempotency-Key`).
+fn resolve_request(req: &RequestSpec, options: &RequestOptions) -> Result<RequestSpec, String> {
+    expand_templates(&with_default_headers(req, &options.default_headers))
+}
+
+/// Builds the request for `req`, which has been through `resolve_request`.
+fn prepare_request(client: &reqwest::Client, req: &RequestSpec, options: &RequestOptions) -> Result<PreparedRequest, String> {
     let mut builder = match req.method.to_uppercase().as_str() {
         "GET" => client.get(&req.url),
         "POST" => client.post(&req.url),
         "PUT" => client.put(&req.url),
         "DELETE" => client.delete(&req.url),
         "PATCH" => client.patch(&req.url),
+        "" => return Err("Missing 'method'".to_string()),
         _ => {
-            return RequestResult {
-                name: request_name,
-                url: req.url.clone(),
-                method: req.method.clone(),
-                status_code: None,
-                status_text: None,
-                success: false,
-                response_time_ms: 0.0,
-                response_body: None,
-                error: Some(format!("Unsupported method: {}", req.method)),
-            };
+            return Err(format!("Unsupported method: {}", req.method));
         }
     };
 
+    let mut header_map = HeaderMap::new();
+    let mut header_errors = Vec::new();
     if let Some(headers) = &req.headers {
-        let mut header_map = HeaderMap::new();
         for (k, v) in headers {
-            if let (Ok(name), Ok(value)) = (HeaderName::from_str(k), HeaderValue::from_str(v)) {
-                header_map.insert(name, value);
+            let Ok(name) = HeaderName::from_str(k) else {
+                header_errors.push(format!("invalid header name {:?}", k));
+                continue;
+            };
+            for value in v.values() {
+                match HeaderValue::from_str(value) {
+                    Ok(value) => {
+                        header_map.append(name.clone(), value);
+                    }
+                    Err(_) => header_errors.push(format!("invalid value for header '{}': {:?}", k, value)),
+                }
+            }
+        }
+    }
+    if !header_errors.is_empty() {
+        header_errors.sort();
+        return Err(format!("Invalid headers: {}", header_errors.join("; ")));
+    }
+
+    let mut trace_id = None;
+    if let Some(trace_header) = &options.trace_header {
+        if !header_map.contains_key(trace_header) {
+            let id = uuid::Uuid::new_v4().to_string();
+            if let Ok(value) = HeaderValue::from_str(&id) {
+                header_map.insert(trace_header.clone(), value);
             }
         }
+        trace_id = header_map.get(trace_header)
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.to_string());
+    }
+
+    if !header_map.is_empty() {
         builder = builder.headers(header_map);
     }
 
     if let Some(params) = &req.params {
-        builder = builder.query(params);
+        builder = builder.query(&to_pairs(params));
+    }
+
+    if let Some(timeout_ms) = req.timeout_ms {
+        builder = builder.timeout(Duration::from_millis(timeout_ms));
+    }
+
+    let mut digest = None;
+    match &req.auth {
+        Some(auth::Auth::Basic { username, password }) => builder = builder.basic_auth(username, password.as_ref()),
+        Some(auth::Auth::Bearer { token }) => builder = builder.bearer_auth(token),
+        Some(auth::Auth::Digest { username, password }) => {
+            if req.body_dir.is_some() {
+                return Err("Digest 'auth' cannot be used with 'body_dir' (a streamed body cannot be resent).".to_string());
+            }
+            digest = Some((username.clone(), password.clone()));
+        }
+        None => {}
     }
 
-    if let Some(body) = &req.body {
+    let body = match &req.graphql {
+        Some(graphql) => {
+            if req.body.is_some() || req.body_base.is_some() || req.form.is_some() {
+                return Err("Cannot use 'graphql' together with 'body', 'body_base', or 'form'.".to_string());
+            }
+            if !req.method.eq_ignore_ascii_case("POST") {
+                return Err(format!("'graphql' is sent as a JSON body, so it requires method POST, not {}.", req.method));
+            }
+            Some(graphql.to_body())
+        }
+        None => effective_body(req)?,
+    };
+    if let Some(body) = &body {
         if req.form.is_some() {
-            return RequestResult {
-                name: request_name,
-                url: req.url.clone(),
-                method: req.method.clone(),
-                status_code: None,
-                status_text: None,
-                success: false,
-                response_time_ms: 0.0,
-                response_body: None,
-                error: Some("Cannot use 'body' and 'form' fields simultaneously.".to_string()),
-            };
+            return Err("Cannot use 'body' and 'form' fields simultaneously.".to_string());
         }
         builder = builder.json(body);
     } else if let Some(form) = &req.form {
-        builder = builder.form(form);
+        builder = builder.form(&to_pairs(form));
     }
+    let request_body = body.or_else(|| req.form.as_ref().and_then(|form| serde_json::to_value(form).ok()));
 
-    let start = std::time::Instant::now();
-    let response = builder.send().await;
-    let elapsed = start.elapsed();
-    let response_time_ms = elapsed.as_secs_f64() * 1000.0;
-
-    match response {
-        Ok(resp) => {
-            let status = resp.status();
-            let status_code = status.as_u16();
-            let status_text = status.canonical_reason().unwrap_or("").to_string();
-            let is_success = status.is_success();
-            
-            let text = resp.text().await.unwrap_or_default();
-            let response_body = serde_json::from_str::<serde_json::Value>(&text).ok();
-            
-            RequestResult {
-                name: request_name,
-                url: req.url.clone(),
-                method: req.method.clone(),
-                status_code: Some(status_code),
-                status_text: Some(status_text),
-                success: is_success,
-                response_time_ms,
-                response_body,
-                error: None,
-            }
+    let mut archive = None;
+    if let Some(dir) = &req.body_dir {
+        if request_body.is_some() {
+            return Err("Cannot use 'body_dir' together with 'body' or 'form'.".to_string());
+        }
+        let files = archive::collect_files(std::path::Path::new(dir), req.body_glob.as_deref())?;
+        let stats = Arc::new(archive::ArchiveStats { files: files.len(), ..Default::default() });
+        builder = builder.body(archive::tar_gz_body(dir.into(), files, stats.clone()));
+        let has_content_type = req.headers.as_ref()
+            .is_some_and(|headers| headers.keys().any(|key| key.eq_ignore_ascii_case("content-type")));
+        if !has_content_type {
+            builder = builder.header(reqwest::header::CONTENT_TYPE, "application/gzip");
+        }
+        archive = Some(stats);
+    } else if req.body_glob.is_some() {
+        return Err("'body_glob' requires 'body_dir'.".to_string());
+    }
+
+    let schema = req.schema.as_ref().map(assertions::load_schema).transpose()?;
+
+    Ok(PreparedRequest { builder, trace_id, request_body, archive, schema, digest })
+}
+
+/// Methods that may be sent twice without changing the outcome, and so are safe to retry.
+const IDEMPOTENT_METHODS: &[&str] = &["GET", "HEAD", "PUT", "DELETE", "OPTIONS"];
+
+/// Pause before the first retry; it doubles for each further one, up to `MAX_RETRY_BACKOFF`.
+const RETRY_BACKOFF: Duration = Duration::from_millis(200);
+const MAX_RETRY_BACKOFF: Duration = Duration::from_secs(10);
+
+/// How many times `req` may be retried. A POST or PATCH could create or change something twice,
+/// so it is only retried with `retry_non_idempotent: true`.
+fn allowed_retries(req: &RequestSpec) -> u32 {
+    let idempotent = IDEMPOTENT_METHODS.contains(&req.method.to_uppercase().as_str());
+    if idempotent || req.retry_non_idempotent.unwrap_or(false) { req.retry.unwrap_or(0) } else { 0 }
+}
+
+/// Failures that may go away by themselves: no response, or a server error.
+fn is_retryable(result: &RequestResult) -> bool {
+    match result.error_kind {
+        Some(ErrorKind::Timeout | ErrorKind::Dns | ErrorKind::Connect | ErrorKind::Network) => true,
+        Some(ErrorKind::Status) => result.status_code.is_some_and(|code| code >= 500),
+        _ => false,
+    }
+}
+
+async fn process_request(client: reqwest::Client, req: RequestSpec, options: Arc<RequestOptions>) -> RequestResult {
+    if let Some(delay) = req.delay_before_ms {
+        tokio::time::sleep(Duration::from_millis(delay)).await;
+    }
+    let resolved = match resolve_request(&req, &options) {
+        Ok(resolved) => resolved,
+        Err(err) => return RequestResult::failed(&req, err),
+    };
+    let retries = allowed_retries(&req);
+    let mut attempt = 1;
+    let mut backoff = RETRY_BACKOFF;
+    loop {
+        let result = send_request(&client, &resolved, &options).await;
+        if attempt > retries || !is_retryable(&result) {
+            return RequestResult { attempts: (attempt > 1).then_some(attempt), ..result };
+        }
+        tokio::time::sleep(backoff).await;
+        backoff = (backoff * 2).min(MAX_RETRY_BACKOFF);
+        attempt += 1;
+    }
+}
+
+/// Sends `req` once.
+async fn send_request(client: &reqwest::Client, req: &RequestSpec, options: &RequestOptions) -> RequestResult {
+    let PreparedRequest { builder, trace_id, request_body, archive, schema, digest } = match prepare_request(client, req, options) {
+        Ok(prepared) => prepared,
+        Err(err) => return RequestResult::failed(req, err),
+    };
+
+    let request = match builder.build() {
+        Ok(request) => request,
+        Err(err) => return RequestResult::failed(req, format!("Invalid request: {}", error_chain(&err))),
+    };
+    let mut exchange = options.keep_exchange.then(|| Exchange {
+        request_line: format!("{} {}", request.method(), request.url()),
+        request_headers: header_pairs(request.headers()),
+        request_body: request.body().map(|body| match body.as_bytes() {
+            Some(bytes) => String::from_utf8_lossy(bytes).to_string(),
+            None => "(streamed body)".to_string(),
+        }),
+        ..Default::default()
+    });
+
+    let started_at = std::time::SystemTime::now();
+    let start = std::time::Instant::now();
+    let response = match (&digest, request.try_clone()) {
+        (Some((username, password)), Some(retry)) => {
+            match auth::send_with_digest(client, request, retry, username, password).await {
+                Ok(response) => Ok(response),
+                Err(auth::DigestError::Http(err)) => Err(err),
+                Err(auth::DigestError::Handshake(message)) => {
+                    return RequestResult {
+                        response_time_ms: start.elapsed().as_secs_f64() * 1000.0,
+                        error: Some(format!("Digest authentication failed: {}", message)),
+                        error_kind: Some(ErrorKind::Spec),
+                        trace_id,
+                        started_at: Some(started_at),
+                        request_body,
+                        exchange,
+                        ..RequestResult::new(req)
+                    };
+                }
+            }
+        }
+        _ => client.execute(request).await,
+    };
+    let elapsed = start.elapsed();
+    let response_time_ms = elapsed.as_secs_f64() * 1000.0;
+    let archive = archive.map(|stats| stats.report());
+
+    match response {
+        Ok(resp) => {
+            let status = resp.status();
+            let status_code = status.as_u16();
+            let status_text = status.canonical_reason().unwrap_or("").to_string();
+            let is_success = status.is_success();
+            let http_version = format!("{:?}", resp.version());
+            if let Some(exchange) = &mut exchange {
+                exchange.status_line = Some(format!("{} {} {}", http_version, status_code, status_text).trim_end().to_string());
+                exchange.response_headers = header_pairs(resp.headers());
+            }
+            
+            let connection = resp.extensions().get::<hyper_util::client::legacy::connect::HttpInfo>().map(|info| {
+                let mut seen = options.connections.lock().unwrap();
+                if seen.insert((info.local_addr(), info.remote_addr())) { ConnectionUse::New } else { ConnectionUse::Reused }
+            });
+            let content_type = resp.headers().get(reqwest::header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(|value| value.to_string());
+            let (bytes, truncated, streamed, body_error) = match options.max_response_bytes {
+                Some(limit) => {
+                    let path = req.save_to.as_ref().map(|template| save_path(template, req));
+                    let (bytes, truncated, saved, body_error) = read_capped_body(resp, limit, path.as_deref()).await;
+                    (bytes, truncated, path.zip(saved), body_error)
+                }
+                None => match resp.bytes().await {
+                    Ok(bytes) => (bytes.to_vec(), false, None, None),
+                    Err(err) => (Vec::new(), false, None, Some(err)),
+                },
+            };
+            let total = start.elapsed();
+            let timing = Timing {
+                headers_ms: response_time_ms,
+                body_ms: (total - elapsed).as_secs_f64() * 1000.0,
+                total_ms: total.as_secs_f64() * 1000.0,
+                connection,
+            };
+            let binary = req.binary.unwrap_or_else(|| is_binary(content_type.as_deref(), &bytes));
+            let binary_size = binary.then_some(bytes.len());
+            if let Some(exchange) = &mut exchange {
+                exchange.response_text = Some(match binary_size {
+                    Some(size) => format!("<binary, {} bytes>", size),
+                    None => String::from_utf8_lossy(&bytes).to_string(),
+                });
+            }
+            let response_body = if binary {
+                None
+            } else {
+                serde_json::from_slice::<serde_json::Value>(&bytes).ok()
+            };
+
+            let captures = req.capture.as_ref()
+                .map(|capture| capture_values(capture, response_body.as_ref()))
+                .unwrap_or_default();
+
+            let mut assertion_outcomes = req.assertions.as_ref()
+                .map(|assertions| assertions::evaluate(assertions, response_body.as_ref()))
+                .unwrap_or_default();
+            if let Some(validator) = &schema {
+                assertion_outcomes.push(assertions::check_schema(validator, response_body.as_ref()));
+            }
+            if let Some(max_ms) = req.max_response_ms {
+                let passed = response_time_ms <= max_ms;
+                assertion_outcomes.push(assertions::AssertionOutcome {
+                    name: "max_response_ms".to_string(),
+                    passed,
+                    message: format!("took {:.1}ms, {} {}ms", response_time_ms, if passed { "within" } else { "allowed" }, max_ms),
+                });
+            }
+            let mut snapshot = None;
+            if let Some(dir) = options.snapshot_dir.as_ref().filter(|_| !binary) {
+                let path = dir.join(format!("{}.json", safe_file_name(req.name.as_deref().unwrap_or("Unnamed"))));
+                let body = response_body.clone().unwrap_or(serde_json::Value::Null);
+                let ignore_paths = req.ignore_paths.as_deref().unwrap_or_default();
+                match snapshot::check(&path, options.update_snapshots, &body, ignore_paths) {
+                    Ok(result) => {
+                        assertion_outcomes.push(result.outcome());
+                        snapshot = Some(result);
+                    }
+                    Err(message) => assertion_outcomes.push(assertions::AssertionOutcome {
+                        name: "snapshot".to_string(),
+                        passed: false,
+                        message,
+                    }),
+                }
+            }
+            let failed_assertions: Vec<String> = assertion_outcomes.iter()
+                .filter(|outcome| !outcome.passed)
+                .map(|outcome| format!("{}: {}", outcome.name, outcome.message))
+                .collect();
+            let (mut error, error_kind) = if let Some(err) = &body_error {
+                (Some(format!("Reading the response body failed: {}", error_chain(err))), Some(ErrorKind::Network))
+            } else if !failed_assertions.is_empty() {
+                // A non-2xx status is the server's failure, even when an assertion caught it too.
+                let kind = if is_success { ErrorKind::Assertion } else { ErrorKind::Status };
+                (Some(format!("Assertion failed: {}", failed_assertions.join("; "))), Some(kind))
+            } else if !is_success {
+                (None, Some(ErrorKind::Status))
+            } else {
+                (None, None)
+            };
+
+            // A failed save is reported, but does not change whether the request passed.
+            let mut saved_to = None;
+            let saved = if body_error.is_some() {
+                req.save_to.as_ref().map(|template| (save_path(template, req), Err(io::Error::other("the response body is incomplete"))))
+            } else {
+                streamed.or_else(|| req.save_to.as_ref().map(|template| {
+                    let path = save_path(template, req);
+                    let result = save_body(&path, &bytes);
+                    (path, result)
+                }))
+            };
+            if let Some((path, result)) = saved {
+                match result {
+                    Ok(()) => saved_to = Some(path),
+                    Err(err) => {
+                        let message = format!("Failed to save response to '{}': {}", path, err);
+                        error = Some(match error {
+                            Some(error) => format!("{}; {}", error, message),
+                            None => message,
+                        });
+                    }
+                }
+            }
+            
+            RequestResult {
+                status_code: Some(status_code),
+                status_text: Some(status_text),
+                http_version: Some(http_version),
+                success: is_success && failed_assertions.is_empty() && body_error.is_none(),
+                response_time_ms,
+                response_body,
+                error,
+                error_kind,
+                trace_id,
+                assertions: assertion_outcomes,
+                archive,
+                captures,
+                saved_to,
+                snapshot,
+                timing: Some(timing),
+                binary_size,
+                body_bytes: bytes.len(),
+                content_type,
+                truncated,
+                raw_body: (options.keep_raw_body.as_deref() == Some(req.name.as_deref().unwrap_or("Unnamed"))).then_some(bytes),
+                started_at: Some(started_at),
+                request_body,
+                exchange,
+                ..RequestResult::new(req)
+            }
+        }
+        Err(err) if err.is_connect() && !err.is_timeout() => {
+            let (error_kind, error_msg) = connect_failure(&err);
+            RequestResult {
+                response_time_ms,
+                error: Some(format!("{}: {}", error_msg, error_chain(&err))),
+                error_kind: Some(error_kind),
+                trace_id,
+                archive,
+                started_at: Some(started_at),
+                request_body,
+                exchange,
+                ..RequestResult::new(req)
+            }
         }
         Err(err) => {
-            let error_msg = if err.is_timeout() {
-                format!("Request timeout ({}s)", timeout)
-            } else if err.is_connect() {
-                "Unable to connect to server".to_string()
+            let error_kind = if err.is_timeout() {
+                ErrorKind::Timeout
+            } else {
+                ErrorKind::Network
+            };
+            // Only the connect timeout fails while connecting; the overall one covers the whole request.
+            let error_msg = if err.is_timeout() && err.is_connect() {
+                match options.connect_timeout {
+                    Some(connect_timeout) => format!("Connect timeout ({})", humantime::format_duration(connect_timeout)),
+                    // The operating system gave up connecting before any limit of ours.
+                    None => "Connect timeout".to_string(),
+                }
+            } else if err.is_timeout() {
+                match req.timeout_ms {
+                    Some(timeout_ms) => format!("Request timeout ({}ms)", timeout_ms),
+                    None => format!("Request timeout ({}s)", options.timeout),
+                }
             } else if err.is_request() {
                 "Invalid request".to_string()
             } else if err.is_body() {
@@ -166,23 +1356,493 @@ async fn process_request(client: reqwest::Client, req: RequestSpec, timeout: u64
             };
             
             RequestResult {
-                name: request_name,
-                url: req.url.clone(),
-                method: req.method.clone(),
-                status_code: None,
-                status_text: None,
-                success: false,
                 response_time_ms,
-                response_body: None,
                 error: Some(format!("{}: {}", error_msg, err)),
+                error_kind: Some(error_kind),
+                trace_id,
+                archive,
+                started_at: Some(started_at),
+                request_body,
+                exchange,
+                ..RequestResult::new(req)
+            }
+        }
+    }
+}
+
+/// Treats a response as binary unless its Content-Type is textual; without one, unless it is valid UTF-8.
+fn is_binary(content_type: Option<&str>, bytes: &[u8]) -> bool {
+    let Some(content_type) = content_type else {
+        return std::str::from_utf8(bytes).is_err();
+    };
+    let mime = content_type.split(';').next().unwrap_or("").trim().to_lowercase();
+    let textual = mime.starts_with("text/")
+        || mime.ends_with("json")
+        || mime.ends_with("xml")
+        || matches!(mime.as_str(), "application/javascript" | "application/x-www-form-urlencoded" | "application/graphql");
+    !textual
+}
+
+/// A request name with characters other than letters, digits, `-`, `_`, and `.` replaced by `_`.
+fn safe_file_name(name: &str) -> String {
+    name.chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .collect()
+}
+
+/// Expands `{name}` in a `save_to` path to the request name, made safe for use as a file name.
+fn save_path(template: &str, req: &RequestSpec) -> String {
+    template.replace("{name}", &safe_file_name(req.name.as_deref().unwrap_or("Unnamed")))
+}
+
+/// Reads the body up to `limit` bytes and tells whether there was more. With `save_to`, the whole
+/// body is still streamed to that file and the result of writing it is returned as well. If the
+/// body breaks off, what arrived so far is returned along with the error.
+async fn read_capped_body(
+    resp: reqwest::Response,
+    limit: usize,
+    save_to: Option<&str>,
+) -> (Vec<u8>, bool, Option<io::Result<()>>, Option<reqwest::Error>) {
+    use tokio::io::AsyncWriteExt;
+    let mut file = match save_to {
+        Some(path) => Some(create_save_file(path).await),
+        None => None,
+    };
+    let mut bytes = Vec::new();
+    let mut truncated = false;
+    let mut body_error = None;
+    let mut stream = resp.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(err) => {
+                body_error = Some(err);
+                break;
+            }
+        };
+        let kept = chunk.len().min(limit - bytes.len());
+        bytes.extend_from_slice(&chunk[..kept]);
+        truncated |= kept < chunk.len();
+        if let Some(Ok(writer)) = &mut file
+            && let Err(err) = writer.write_all(&chunk).await
+        {
+            file = Some(Err(err));
+        }
+        if truncated && !matches!(file, Some(Ok(_))) {
+            break;
+        }
+    }
+    if truncated {
+        // Don't let a character cut in half make a text body look binary.
+        if let Err(err) = std::str::from_utf8(&bytes)
+            && err.error_len().is_none()
+        {
+            bytes.truncate(err.valid_up_to());
+        }
+    }
+    let saved = match file {
+        Some(Ok(mut writer)) => Some(writer.flush().await),
+        Some(Err(err)) => Some(Err(err)),
+        None => None,
+    };
+    (bytes, truncated, saved, body_error)
+}
+
+async fn create_save_file(path: &str) -> io::Result<tokio::io::BufWriter<tokio::fs::File>> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        tokio::fs::create_dir_all(parent).await?;
+    }
+    Ok(tokio::io::BufWriter::new(tokio::fs::File::create(path).await?))
+}
+
+fn save_body(path: &str, bytes: &[u8]) -> io::Result<()> {
+    if let Some(parent) = std::path::Path::new(path).parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, bytes)
+}
+
+/// Looks up each `variable: "/json/pointer"` in the response body; missing values are left out.
+fn capture_values(capture: &HashMap<String, String>, body: Option<&serde_json::Value>) -> BTreeMap<String, serde_json::Value> {
+    let Some(body) = body else { return BTreeMap::new() };
+    capture.iter()
+        .filter_map(|(variable, pointer)| body.pointer(pointer).map(|value| (variable.clone(), value.clone())))
+        .collect()
+}
+
+fn check_capture_names(requests: &[RequestSpec]) -> Result<(), String> {
+    let mut owners: HashMap<&str, &str> = HashMap::new();
+    for req in requests {
+        let name = req.name.as_deref().unwrap_or("Unnamed");
+        for variable in req.capture.iter().flat_map(|capture| capture.keys()) {
+            if let Some(owner) = owners.insert(variable, name) {
+                return Err(format!("Variable '{}' is captured by both '{}' and '{}'", variable, owner, name));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Each request needs a snapshot file of its own.
+fn check_snapshot_names(requests: &[RequestSpec]) -> Result<(), String> {
+    let mut owners: HashMap<String, &str> = HashMap::new();
+    for req in requests {
+        let name = req.name.as_deref().unwrap_or("Unnamed");
+        if let Some(owner) = owners.insert(safe_file_name(name), name) {
+            return Err(format!("Requests '{}' and '{}' would share a snapshot file; give them distinct names", owner, name));
+        }
+    }
+    Ok(())
+}
+
+/// Variables visible to expressions: everything captured so far, plus `env` holding the environment.
+fn expression_variables(results: &[RequestResult]) -> HashMap<String, serde_json::Value> {
+    let mut variables: HashMap<String, serde_json::Value> = results.iter()
+        .flat_map(|result| result.captures.clone())
+        .collect();
+    let environment: serde_json::Map<String, serde_json::Value> = env::vars()
+        .map(|(name, value)| (name, serde_json::Value::String(value)))
+        .collect();
+    variables.entry("env".to_string()).or_insert(serde_json::Value::Object(environment));
+    variables
+}
+
+/// Evaluates `run_if` and `skip_if`, returning why the request should be skipped, if it should.
+fn skip_condition(req: &RequestSpec, variables: &HashMap<String, serde_json::Value>) -> Result<Option<String>, String> {
+    let evaluate = |field: &str, condition: &str| match expr::evaluate_condition(condition, variables) {
+        Ok(serde_json::Value::Bool(value)) => Ok(value),
+        Ok(other) => Err(format!("Invalid {} '{}': expected a boolean, got {}", field, condition, other)),
+        Err(err) => Err(format!("Invalid {} '{}': {}", field, condition, err)),
+    };
+    if let Some(condition) = &req.run_if
+        && !evaluate("run_if", condition)?
+    {
+        return Ok(Some(format!("run_if is false: {}", condition)));
+    }
+    if let Some(condition) = &req.skip_if
+        && evaluate("skip_if", condition)?
+    {
+        return Ok(Some(format!("skip_if is true: {}", condition)));
+    }
+    Ok(None)
+}
+
+/// The result to report instead of sending `req`, when its conditions say so.
+fn conditional_result(req: &RequestSpec, results: &[RequestResult]) -> Option<RequestResult> {
+    if req.run_if.is_none() && req.skip_if.is_none() {
+        return None;
+    }
+    match skip_condition(req, &expression_variables(results)) {
+        Ok(None) => None,
+        Ok(Some(reason)) => Some(RequestResult::skipped(req, &reason)),
+        Err(err) => Some(RequestResult::failed(req, err)),
+    }
+}
+
+/// Hooks run only when allowed, and only one request at a time so they cannot interfere.
+fn check_hooks(requests: &[RequestSpec], args: &Args) -> Result<(), String> {
+    let Some(req) = requests.iter().find(|req| req.before.is_some() || req.after.is_some()) else {
+        return Ok(());
+    };
+    let name = req.name.as_deref().unwrap_or("Unnamed");
+    if !args.allow_hooks {
+        Err(format!("Request '{}' has a before/after hook; pass --allow-hooks to run hook commands", name))
+    } else if !args.sequential {
+        Err(format!("Request '{}' has a before/after hook, which needs --sequential", name))
+    } else {
+        Ok(())
+    }
+}
+
+/// Sends `req` between its `before` and `after` hooks. A failing `before` hook fails the request
+/// without sending it; a failing `after` hook fails a request that otherwise passed. Variables the
+/// hooks print are added to the request's captures, the response's own captures taking precedence.
+async fn process_with_hooks(client: reqwest::Client, req: RequestSpec, options: Arc<RequestOptions>) -> RequestResult {
+    let name = req.name.as_deref().unwrap_or("Unnamed").to_string();
+    let mut captures = BTreeMap::new();
+    if let Some(command) = &req.before {
+        match hook::run(command, &[("JHTTP_REQUEST_NAME", name.clone())]).await {
+            Ok(printed) => captures = printed,
+            Err(err) => return RequestResult {
+                error: Some(format!("before hook {}", err)),
+                error_kind: Some(ErrorKind::Hook),
+                ..RequestResult::new(&req)
+            },
+        }
+    }
+    let after = req.after.clone();
+    let mut result = process_request(client, req, options).await;
+    captures.append(&mut result.captures);
+    result.captures = captures;
+
+    if let Some(command) = &after {
+        let env = [
+            ("JHTTP_REQUEST_NAME", name),
+            ("JHTTP_STATUS_CODE", result.status_code.map(|code| code.to_string()).unwrap_or_default()),
+            ("JHTTP_SUCCESS", result.success.to_string()),
+        ];
+        match hook::run(command, &env).await {
+            Ok(printed) => {
+                for (variable, value) in printed {
+                    result.captures.entry(variable).or_insert(value);
+                }
+            }
+            Err(err) => {
+                let message = format!("after hook {}", err);
+                result.error = Some(match result.error.take() {
+                    Some(error) => format!("{}; {}", error, message),
+                    None => message,
+                });
+                if result.success {
+                    result.success = false;
+                    result.error_kind = Some(ErrorKind::Hook);
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Every `depends_on` entry must name exactly one request, and the dependencies must not form a cycle.
+fn check_dependencies(requests: &[RequestSpec]) -> Result<(), String> {
+    let mut by_name: HashMap<&str, Vec<&RequestSpec>> = HashMap::new();
+    for req in requests {
+        by_name.entry(req.name.as_deref().unwrap_or("Unnamed")).or_default().push(req);
+    }
+    for req in requests {
+        for dependency in req.depends_on.iter().flatten() {
+            match by_name.get(dependency.as_str()).map(Vec::len) {
+                None => return Err(format!(
+                    "Request '{}' depends on '{}', which is not in the request file",
+                    req.name.as_deref().unwrap_or("Unnamed"), dependency
+                )),
+                Some(1) => {}
+                Some(_) => return Err(format!("'depends_on' cannot refer to '{}': several requests have that name", dependency)),
+            }
+        }
+    }
+
+    // Depth-first search; `path` holds the requests currently being visited.
+    fn visit<'a>(
+        name: &'a str,
+        by_name: &HashMap<&'a str, Vec<&'a RequestSpec>>,
+        path: &mut Vec<&'a str>,
+        done: &mut std::collections::HashSet<&'a str>,
+    ) -> Result<(), String> {
+        if done.contains(name) {
+            return Ok(());
+        }
+        if let Some(start) = path.iter().position(|visiting| *visiting == name) {
+            let mut cycle = path[start..].to_vec();
+            cycle.push(name);
+            return Err(format!("Dependency cycle: {}", cycle.join(" -> ")));
+        }
+        path.push(name);
+        for req in by_name.get(name).into_iter().flatten() {
+            for dependency in req.depends_on.iter().flatten() {
+                visit(dependency, by_name, path, done)?;
+            }
+        }
+        path.pop();
+        done.insert(name);
+        Ok(())
+    }
+    let mut done = std::collections::HashSet::new();
+    for req in requests {
+        visit(req.name.as_deref().unwrap_or("Unnamed"), &by_name, &mut Vec::new(), &mut done)?;
+    }
+    Ok(())
+}
+
+/// Reorders requests so each one comes after the requests it depends on, otherwise keeping file order.
+fn dependency_order(requests: Vec<RequestSpec>) -> Vec<RequestSpec> {
+    let names: std::collections::HashSet<String> = requests.iter().filter_map(|req| req.name.clone()).collect();
+    let mut placed: std::collections::HashSet<String> = std::collections::HashSet::new();
+    let mut remaining = requests;
+    let mut ordered = Vec::with_capacity(remaining.len());
+    while !remaining.is_empty() {
+        let ready = remaining.iter()
+            .position(|req| req.depends_on.iter().flatten().all(|dep| placed.contains(dep) || !names.contains(dep)))
+            .expect("dependency cycles are rejected when the file is loaded");
+        let req = remaining.remove(ready);
+        placed.extend(req.name.clone());
+        ordered.push(req);
+    }
+    ordered
+}
+
+enum DependencyState {
+    Ready,
+    Waiting,
+    /// A dependency failed or was skipped; holds the reason to skip the request with.
+    Failed(String),
+}
+
+/// `outcomes` maps finished requests to whether they succeeded. A dependency that is neither finished
+/// nor in `in_run` (e.g. not selected by --only) does not hold the request back.
+fn dependency_state(req: &RequestSpec, outcomes: &HashMap<String, bool>, in_run: &std::collections::HashSet<String>) -> DependencyState {
+    let mut state = DependencyState::Ready;
+    for dependency in req.depends_on.iter().flatten() {
+        match outcomes.get(dependency) {
+            Some(false) => return DependencyState::Failed(format!("Dependency '{}' did not succeed", dependency)),
+            Some(true) => {}
+            None if in_run.contains(dependency) => state = DependencyState::Waiting,
+            None => {}
+        }
+    }
+    state
+}
+
+fn check_invariants(invariants: &[Invariant], results: &[RequestResult]) -> Vec<assertions::AssertionOutcome> {
+    let variables = expression_variables(results);
+    invariants.iter()
+        .map(|invariant| {
+            let (passed, message) = match expr::evaluate(&invariant.expr, &variables) {
+                Ok(serde_json::Value::Bool(true)) => (true, "Holds".to_string()),
+                Ok(serde_json::Value::Bool(false)) => (false, format!("Evaluated to false: {}", invariant.expr)),
+                Ok(other) => (false, format!("Expected a boolean, got {}", other)),
+                Err(err) => (false, err),
+            };
+            assertions::AssertionOutcome {
+                name: invariant.name.clone().unwrap_or_else(|| invariant.expr.clone()),
+                passed,
+                message,
             }
+        })
+        .collect()
+}
+
+/// Waits for every permit (group limit first, then the global one) before sending,
+/// so the most restrictive limit always wins.
+async fn process_limited(client: reqwest::Client, req: RequestSpec, options: Arc<RequestOptions>, limits: Vec<Arc<Semaphore>>) -> RequestResult {
+    let mut permits = Vec::with_capacity(limits.len());
+    for limit in limits {
+        permits.push(limit.acquire_owned().await.expect("concurrency semaphore closed"));
+    }
+    process_request(client, req, options).await
+}
+
+fn group_limits(requests: &[RequestSpec]) -> Result<HashMap<String, Arc<Semaphore>>, String> {
+    let mut limits: HashMap<String, usize> = HashMap::new();
+    for req in requests {
+        let Some(max) = req.max_concurrency else { continue };
+        if max == 0 {
+            return Err(format!("'max_concurrency' must be at least 1 (request '{}')", req.name.as_deref().unwrap_or("Unnamed")));
+        }
+        if let Some(group) = &req.group {
+            let limit = limits.entry(group.clone()).or_insert(max);
+            *limit = (*limit).min(max);
+        }
+    }
+    Ok(limits.into_iter()
+        .map(|(group, max)| (group, Arc::new(Semaphore::new(max))))
+        .collect())
+}
+
+/// Runs `future` to completion, or gives up with `None` once `deadline` has passed.
+async fn within<F: std::future::Future>(deadline: Option<tokio::time::Instant>, future: F) -> Option<F::Output> {
+    match deadline {
+        Some(deadline) => tokio::time::timeout_at(deadline, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+/// Decides whether `result` should stop the run, returning what triggered it; `earlier` holds the
+/// results that came before it.
+fn abort_reason(result: &RequestResult, earlier: &[RequestResult], args: &Args) -> Option<String> {
+    if args.bail_on_assert && result.error_kind == Some(ErrorKind::Assertion) {
+        return Some(format!("--bail-on-assert: assertion failed in '{}'", result.name));
+    }
+    if let Some(max_failures) = args.max_failures {
+        let failures = earlier.iter().chain(std::iter::once(result))
+            .filter(|result| !result.success && !result.skipped)
+            .count();
+        if failures >= max_failures {
+            return Some(format!("--max-failures: {} requests failed", failures));
+        }
+    }
+    None
+}
+
+/// How much of a JSON response body the pretty output shows (use --verbose for all of it).
+const BODY_PREVIEW_CHARS: usize = 500;
+
+/// Headers whose values are only shown at `-vv`.
+const SECRET_HEADERS: &[&str] = &["authorization", "proxy-authorization"];
+
+/// How many differences of a mismatching snapshot the pretty output lists.
+const SNAPSHOT_DIFF_LINES: usize = 20;
+
+fn print_snapshot_diff(snapshot: &snapshot::SnapshotResult) {
+    println!("{} {}", "Snapshot diff:".bright_white().bold(), "(- snapshot, + response)".bright_black());
+    for difference in snapshot.differences.iter().take(SNAPSHOT_DIFF_LINES) {
+        let path = if difference.path.is_empty() { "/" } else { &difference.path };
+        if let Some(expected) = &difference.expected {
+            println!("{}", format!("- {}: {}", path, expected).red());
+        }
+        if let Some(actual) = &difference.actual {
+            println!("{}", format!("+ {}: {}", path, actual).green());
+        }
+    }
+    if snapshot.differences.len() > SNAPSHOT_DIFF_LINES {
+        println!("{}", format!("... and {} more", snapshot.differences.len() - SNAPSHOT_DIFF_LINES).bright_black().italic());
+    }
+}
+
+fn print_exchange(exchange: &Exchange, show_secrets: bool) {
+    let print_headers = |prefix: &str, headers: &[(String, String)]| {
+        for (name, value) in headers {
+            let value = if !show_secrets && SECRET_HEADERS.contains(&name.to_lowercase().as_str()) {
+                "*** (shown with -vv)"
+            } else {
+                value.as_str()
+            };
+            println!("{} {}: {}", prefix.bright_black(), name.bright_white(), value);
+        }
+    };
+
+    println!();
+    println!("{} {}", ">".bright_black(), exchange.request_line.bright_yellow());
+    print_headers(">", &exchange.request_headers);
+    if let Some(body) = &exchange.request_body {
+        println!("{}", ">".bright_black());
+        println!("{}", body);
+    }
+
+    let Some(status_line) = &exchange.status_line else { return };
+    println!();
+    println!("{} {}", "<".bright_black(), status_line.bright_yellow());
+    print_headers("<", &exchange.response_headers);
+    if let Some(text) = exchange.response_text.as_deref().filter(|text| !text.is_empty()) {
+        println!("{}", "<".bright_black());
+        println!("{}", text);
+    }
+}
+
+/// Prints pretty-printed JSON, cut off after BODY_PREVIEW_CHARS.
+fn print_json_preview(json: &serde_json::Value) {
+    let pretty = serde_json::to_string_pretty(json).unwrap_or_default();
+    match pretty.char_indices().nth(BODY_PREVIEW_CHARS) {
+        Some((cut, _)) => {
+            println!("{}", highlight::json(&pretty[..cut]));
+            println!("{}", format!("... ({} bytes truncated)", pretty.len() - cut).bright_black().italic());
         }
+        None => println!("{}", highlight::json(&pretty)),
     }
 }
 
-fn print_result(result: &RequestResult, total_requests: usize, request_index: usize) {
-    println!("\n{} {}", 
-        format!("[{}/{}]", request_index, total_requests).bright_cyan(),
+/// `finished` is when the request finished, counted from the start of the run; it is shown in concurrent runs.
+fn print_result(
+    result: &RequestResult,
+    total_requests: usize,
+    show_secrets: bool,
+    selection: Option<&select::Path>,
+    finished: Option<Duration>,
+) {
+    let finished = finished.map(|elapsed| format!("+{:.2}s ", elapsed.as_secs_f64())).unwrap_or_default();
+    println!("\n{}{} {}", 
+        finished.bright_black(),
+        format!("[{}/{}]", result.position, total_requests).bright_cyan(),
         result.name.bright_white().bold()
     );
     println!("{} {} {}", 
@@ -190,12 +1850,21 @@ fn print_result(result: &RequestResult, total_requests: usize, request_index: us
         result.method.to_uppercase().bright_yellow(),
         result.url.bright_black()
     );
+    if let Some(file) = &result.file {
+        println!("{} {}", "File:".bright_black(), file.bright_black());
+    }
+
+    if result.skipped {
+        println!("{} {}", "⏭️  Skipped:".yellow().bold(), result.error.as_deref().unwrap_or("").bright_black());
+        println!("{}", "-".repeat(60).bright_black());
+        return;
+    }
 
     if let Some(status_code) = result.status_code {
         let status_text = result.status_text.as_deref().unwrap_or("");
         let status_display = if result.success {
             format!("✅ Status: {} {}", status_code, status_text).green()
-        } else if status_code >= 400 && status_code < 500 {
+        } else if (400..500).contains(&status_code) {
             format!("⚠️  Status: {} {}", status_code, status_text).yellow()
         } else if status_code >= 500 {
             format!("❌ Status: {} {}", status_code, status_text).red()
@@ -207,42 +1876,203 @@ fn print_result(result: &RequestResult, total_requests: usize, request_index: us
 
     println!("{} {:.2}s", "Response time:".bright_black(), result.response_time_ms / 1000.0);
 
+    if let Some(attempts) = result.attempts {
+        println!("{} {}", "Attempts:".bright_black(), attempts);
+    }
+
+    if let Some(trace_id) = &result.trace_id {
+        println!("{} {}", "Trace id:".bright_black(), trace_id);
+    }
+
+    if let Some(archive) = &result.archive {
+        println!("{} {} files, {} bytes (tar.gz)", "Uploaded archive:".bright_black(), archive.files, archive.bytes);
+    }
+
+    if let Some(path) = &result.saved_to {
+        println!("{} {}", "Saved to:".bright_black(), path);
+    }
+
+    for outcome in &result.assertions {
+        if outcome.passed {
+            println!("{} {} {}", "✅ Assert".green(), outcome.name.green(), outcome.message.bright_black());
+        } else {
+            println!("{} {} {}", "❌ Assert".red(), outcome.name.red(), outcome.message.bright_black());
+        }
+    }
+
+    if let Some(snapshot) = result.snapshot.as_ref().filter(|snapshot| snapshot.status == snapshot::SnapshotStatus::Mismatch) {
+        print_snapshot_diff(snapshot);
+    }
+
     if let Some(error) = &result.error {
          println!("{} {}", "❌ Error:".red().bold(), error.bright_black());
     }
 
+    if let Some(exchange) = &result.exchange {
+        if let Some(timing) = &result.timing {
+            let connection = match timing.connection {
+                Some(ConnectionUse::New) => " (new connection)",
+                Some(ConnectionUse::Reused) => " (reused connection)",
+                None => "",
+            };
+            println!("{} headers {:.1}ms + body {:.1}ms = {:.1}ms{}",
+                "Timing:".bright_black(), timing.headers_ms, timing.body_ms, timing.total_ms, connection.bright_black());
+        }
+        if result.status_code.is_some() {
+            let content_type = result.content_type.as_deref().map(|content_type| format!(" ({})", content_type)).unwrap_or_default();
+            println!("{} {} bytes{}", "Body size:".bright_black(), result.body_bytes, content_type.bright_black());
+        }
+        print_exchange(exchange, show_secrets);
+        println!("{}", "-".repeat(60).bright_black());
+        return;
+    }
+
+    if let (Some(path), Some(json)) = (selection, &result.response_body) {
+        println!("\n{} {}", "Response body".bright_white().bold(), format!("({}):", path).bright_black());
+        let matches = select::select(path, json);
+        if matches.is_empty() {
+            println!("{}", format!("(no match for '{}')", path).yellow());
+        }
+        for value in matches {
+            print_json_preview(value);
+        }
+        println!("{}", "-".repeat(60).bright_black());
+        return;
+    }
+
     println!("\n{}", "Response body:".bright_white().bold());
     if let Some(json) = &result.response_body {
-        let pretty = serde_json::to_string_pretty(json).unwrap_or_default();
-        if pretty.len() > 500 {
-            println!("{}", &pretty[..500].bright_black());
-            println!("{}", format!("... ({} bytes truncated)", pretty.len() - 500).bright_black().italic());
-        } else {
-            println!("{}", pretty.bright_black());
-        }
+        print_json_preview(json);
+    } else if let Some(size) = result.binary_size {
+        println!("{}", format!("<binary, {} bytes>", size).bright_black());
     } else {
         println!("{}", "(empty)".bright_black());
     }
+    if result.truncated {
+        println!("{}", "(truncated by --max-response-bytes)".yellow());
+    }
     println!("{}", "-".repeat(60).bright_black());
 }
 
-fn print_summary_box(total: usize, success: usize, failed: usize, success_rate: f64, failed_requests: Vec<String>) {
-    let mut lines = vec![
-        format!("Total: {}", total),
-        format!("Success: {}", success),
-        format!("Failed: {}", failed),
-        format!("Success rate: {:.1}%", success_rate),
+/// `rows` of (label, value, color) as indented lines, with the values lined up after the widest label.
+fn aligned_rows(rows: Vec<(String, String, Option<Color>)>) -> Vec<ColoredString> {
+    let label_width = rows.iter().map(|(label, _, _)| unicode_width::UnicodeWidthStr::width(label.as_str())).max().unwrap_or(0);
+    rows.into_iter()
+        .map(|(label, value, color)| {
+            let padding = " ".repeat(label_width - unicode_width::UnicodeWidthStr::width(label.as_str()));
+            let line = format!("  {}{}  {}", label, padding, value);
+            match color {
+                Some(color) => line.color(color),
+                None => line.normal(),
+            }
+        })
+        .collect()
+}
+
+/// `bytes` in B, KB, or MB (of 1024), e.g. "12.3 KB".
+fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        0..1024 => format!("{} B", bytes),
+        1024..1_048_576 => format!("{:.1} KB", bytes as f64 / 1024.0),
+        _ => format!("{:.1} MB", bytes as f64 / 1_048_576.0),
+    }
+}
+
+/// With `show_errors`, each failed request is listed with the reason it failed.
+fn print_summary_box(summary: &TestSummary, show_errors: bool) {
+    let success = format!("Success: {}", summary.success);
+    let failed = format!("Failed: {}", summary.failed);
+    let mut lines: Vec<ColoredString> = vec![
+        format!("Total: {}", summary.total).normal(),
+        if summary.success > 0 { success.green() } else { success.normal() },
+        if summary.failed > 0 { failed.red() } else { failed.normal() },
     ];
+    if summary.skipped > 0 {
+        lines.push(format!("Skipped: {}", summary.skipped).yellow());
+    }
+    lines.push(format!("Success rate: {:.1}%", summary.success_rate).normal());
+    if let Some(reason) = &summary.aborted {
+        lines.push(format!("Aborted: {}", reason).red());
+    }
+    if !summary.invariants.is_empty() {
+        let held = summary.invariants.iter().filter(|outcome| outcome.passed).count();
+        lines.push(format!("Invariants: {}/{} held", held, summary.invariants.len()).normal());
+    }
+
+    if !summary.status_classes.is_empty() {
+        lines.push("".normal());
+        lines.push("Status Codes:".normal());
+        lines.extend(aligned_rows(summary.status_classes.iter()
+            .map(|(class, &count)| {
+                let color = match class.as_str() {
+                    "2xx" => Color::Green,
+                    "3xx" => Color::Blue,
+                    "4xx" => Color::Yellow,
+                    _ => Color::Red,
+                };
+                let label = if class == "errors" { "Errors".to_string() } else { class.clone() };
+                (label, count.to_string(), Some(color))
+            })
+            .collect()));
+    }
+
+    // A method breakdown only tells something when more than one method was used.
+    if summary.methods.len() > 1 {
+        lines.push("".normal());
+        lines.push("By Method:".normal());
+        lines.extend(aligned_rows(summary.methods.iter()
+            .map(|method| {
+                match method.failed {
+                    0 => (method.method.clone(), method.total.to_string(), None),
+                    failed => (method.method.clone(), format!("{} ({} failed)", method.total, failed), Some(Color::Red)),
+                }
+            })
+            .collect()));
+    }
 
+    if let Some(slowest) = &summary.slowest {
+        lines.push("".normal());
+        lines.push(format!("Slowest: {} {} ({:.2}s)", slowest.method, slowest.name, slowest.response_time_ms / 1000.0).normal());
+    }
+    if summary.total_bytes > 0 {
+        lines.push(format!("Received: {}", format_bytes(summary.total_bytes)).normal());
+    }
+
+    let failed_requests: Vec<&RequestResult> = summary.results
+        .iter()
+        .filter(|r| !r.success && !r.skipped)
+        .collect();
     if !failed_requests.is_empty() {
-        lines.push("".to_string());
-        lines.push("Failed Requests:".to_string());
-        for name in failed_requests {
-            lines.push(format!("  - {}", name));
+        lines.push("".normal());
+        lines.push("Failed Requests:".normal());
+        for result in failed_requests {
+            let name = match &result.file {
+                Some(file) => format!("{} ({})", result.name, file),
+                None => result.name.clone(),
+            };
+            if show_errors {
+                lines.push(format!("  - {}: {}", name, report::failure_message(result)).red());
+            } else {
+                lines.push(format!("  - {}", name).red());
+            }
         }
     }
 
-    let max_line_width = lines.iter().map(|s| unicode_width::UnicodeWidthStr::width(s.as_str())).max().unwrap_or(0);
+    let skipped_requests: Vec<&str> = summary.results
+        .iter()
+        .filter(|r| r.skipped)
+        .map(|r| r.name.as_str())
+        .collect();
+    if !skipped_requests.is_empty() {
+        lines.push("".normal());
+        lines.push("Skipped Requests:".normal());
+        for name in skipped_requests {
+            lines.push(format!("  - {}", name).yellow());
+        }
+    }
+
+    // `&*line` is the text without color codes, so the widths are what the terminal shows.
+    let max_line_width = lines.iter().map(|s| unicode_width::UnicodeWidthStr::width(&**s)).max().unwrap_or(0);
     let title = "Test Summary";
     let title_width = unicode_width::UnicodeWidthStr::width(title);
     let box_width = std::cmp::max(max_line_width, title_width) + 4;
@@ -251,84 +2081,1144 @@ fn print_summary_box(total: usize, success: usize, failed: usize, success_rate:
     let padding_total = box_width - title_width;
     let padding_left = padding_total / 2;
     let padding_right = padding_total - padding_left;
-    println!("│{}{}{}│", " ".repeat(padding_left), title, " ".repeat(padding_right));
+    println!("│{}{}{}│", " ".repeat(padding_left), title.bold(), " ".repeat(padding_right));
     println!("├{}┤", "─".repeat(box_width));
     
     for line in lines {
-        let content = format!("  {}", line);
-        let line_width = unicode_width::UnicodeWidthStr::width(content.as_str());
+        let line_width = unicode_width::UnicodeWidthStr::width(&*line) + 2;
         let padding = " ".repeat(box_width - line_width);
-        println!("│{}{}│", content, padding);
+        println!("│  {}{}│", line, padding);
     }
 
     println!("└{}┘", "─".repeat(box_width));
 }
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    dotenvy::dotenv().ok();
+fn print_host_table(hosts: &[HostSummary]) {
+    let host_width = hosts.iter()
+        .map(|h| unicode_width::UnicodeWidthStr::width(h.host.as_str()))
+        .chain(std::iter::once("Host".len()))
+        .max()
+        .unwrap_or(0);
 
-    let args = Args::parse();
-    let data = fs::read_to_string(&args.file)?;
-    let substituted_data = substitute_env_vars(&data);
-    let requests: Vec<RequestSpec> = serde_json::from_str(&substituted_data)?;
+    println!("\n{}", "Requests per host".bright_white().bold());
+    println!("{}", format!(
+        "{:<width$}  {:>8}  {:>8}  {:>8}  {:>8}",
+        "Host", "Total", "Success", "Failed", "Rate",
+        width = host_width
+    ).bright_black());
+    for host in hosts {
+        let padding = " ".repeat(host_width - unicode_width::UnicodeWidthStr::width(host.host.as_str()));
+        let rate = format!("{:.1}%", host.success_rate);
+        let rate = if host.failed == 0 { rate.green() } else { rate.red() };
+        println!(
+            "{}{}  {:>8}  {:>8}  {:>8}  {:>8}",
+            host.host, padding, host.total, host.success, host.failed, rate
+        );
+    }
+}
+
+/// Tells DNS, refused-connection, and TLS failures apart by looking through the error's causes.
+fn connect_failure(err: &reqwest::Error) -> (ErrorKind, &'static str) {
+    let chain = error_chain(err).to_lowercase();
+    let mut source: Option<&dyn std::error::Error> = Some(err);
+    let mut refused = false;
+    while let Some(cause) = source {
+        refused |= cause.downcast_ref::<io::Error>().is_some_and(|io| io.kind() == io::ErrorKind::ConnectionRefused);
+        source = cause.source();
+    }
+    if chain.contains("dns error") || chain.contains("failed to lookup address") {
+        (ErrorKind::Dns, "Could not resolve host")
+    } else if refused {
+        (ErrorKind::Connect, "Connection refused")
+    } else if ["tls", "ssl", "certificate", "handshake"].iter().any(|marker| chain.contains(marker)) {
+        (ErrorKind::Tls, "TLS handshake failed")
+    } else {
+        (ErrorKind::Connect, "Unable to connect to server")
+    }
+}
+
+fn error_chain(err: &dyn std::error::Error) -> String {
+    let mut message = err.to_string();
+    let mut source = err.source();
+    while let Some(cause) = source {
+        message.push_str(&format!(": {}", cause));
+        source = cause.source();
+    }
+    message
+}
 
-    let output_json = args.output.as_deref() == Some("json");
+fn print_invariants(invariants: &[assertions::AssertionOutcome]) {
+    println!("\n{}", "Invariants".bright_white().bold());
+    for outcome in invariants {
+        if outcome.passed {
+            println!("{} {}", "✅".green(), outcome.name.green());
+        } else {
+            println!("{} {} {}", "❌".red(), outcome.name.red(), outcome.message.bright_black());
+        }
+    }
+}
+
+fn print_drift_report(drift: &drift::DriftReport) {
+    println!("\n{}", "Response drift".bright_white().bold());
+    if drift.baseline_created {
+        println!("{}", format!("No previous state; recorded baseline in {}", drift.state_file).bright_black());
+        return;
+    }
+    if drift.added.is_empty() && drift.removed.is_empty() && drift.changed.is_empty() {
+        println!("{}", "No response changes since the last run".green());
+        return;
+    }
+    for name in &drift.changed {
+        println!("{} {}", "~ changed:".yellow(), name);
+    }
+    for name in &drift.added {
+        println!("{} {}", "+ added:  ".green(), name);
+    }
+    for name in &drift.removed {
+        println!("{} {}", "- removed:".red(), name);
+    }
+}
+
+fn read_tls_file(path: &str, what: &str) -> Result<Vec<u8>, String> {
+    fs::read(path).map_err(|err| format!("Failed to read {} '{}': {}", what, path, err))
+}
+
+fn load_identity(args: &Args) -> Result<Option<reqwest::Identity>, String> {
+    if let (Some(cert_path), Some(key_path)) = (&args.client_cert, &args.client_key) {
+        let cert = read_tls_file(cert_path, "client certificate")?;
+        let key = read_tls_file(key_path, "client key")?;
+        return reqwest::Identity::from_pkcs8_pem(&cert, &key)
+            .map(Some)
+            .map_err(|err| format!("Invalid client certificate/key ('{}', '{}'): {}", cert_path, key_path, error_chain(&err)));
+    }
+    if let Some(identity_path) = &args.identity {
+        let der = read_tls_file(identity_path, "identity")?;
+        return reqwest::Identity::from_pkcs12_der(&der, &args.identity_password)
+            .map(Some)
+            .map_err(|err| format!("Invalid PKCS#12 identity '{}': {}", identity_path, error_chain(&err)));
+    }
+    Ok(None)
+}
+
+fn is_selected(position: usize, req: &RequestSpec, args: &Args) -> bool {
+    let name = req.name.as_deref();
+    if !args.only.is_empty() {
+        let position = position.to_string();
+        let listed = args.only.iter().any(|only| Some(only.as_str()) == name || *only == position);
+        if !listed {
+            return false;
+        }
+    }
+    if let Some(filter) = &args.filter {
+        let matches = name.is_some_and(|name| name.to_lowercase().contains(&filter.to_lowercase()));
+        if !matches {
+            return false;
+        }
+    }
+    if !args.tag.is_empty() {
+        let tagged = req.tags.as_ref().is_some_and(|tags| tags.iter().any(|tag| args.tag.contains(tag)));
+        if !tagged {
+            return false;
+        }
+    }
+    true
+}
 
-    let client = reqwest::Client::builder()
+fn print_dry_run(client: &reqwest::Client, requests: &[RequestSpec], options: &RequestOptions, redactor: Option<&redact::Redactor>) {
+    let text = |text: &str| redactor.map_or_else(|| text.to_string(), |redactor| redactor.text(text));
+    let total = requests.len();
+    for (index, req) in requests.iter().enumerate() {
+        println!("\n{} {}",
+            format!("[{}/{}]", index + 1, total).bright_cyan(),
+            req.name.as_deref().unwrap_or("Unnamed").bright_white().bold()
+        );
+
+        let request = resolve_request(req, options)
+            .and_then(|req| prepare_request(client, &req, options))
+            .and_then(|prepared| prepared.builder.build().map_err(|err| error_chain(&err)));
+        let request = match request {
+            Ok(request) => request,
+            Err(err) => {
+                println!("{} {}", "❌ Error:".red().bold(), err.bright_black());
+                println!("{}", "-".repeat(60).bright_black());
+                continue;
+            }
+        };
+
+        println!("{} {} {}",
+            "Method:".bright_black(),
+            request.method().as_str().bright_yellow(),
+            text(request.url().as_str())
+        );
+        let mut resolved_text = request.url().to_string();
+        if !request.headers().is_empty() {
+            println!("{}", "Headers:".bright_black());
+            for (name, value) in request.headers() {
+                let value = String::from_utf8_lossy(value.as_bytes());
+                let shown = redactor.map_or_else(|| value.to_string(), |redactor| redactor.header(name.as_str(), &value));
+                println!("  {}: {}", name.as_str().bright_white(), shown);
+                resolved_text.push_str(&value);
+            }
+        }
+        if let Some(bytes) = request.body().and_then(|body| body.as_bytes()) {
+            println!("{}", "Body:".bright_black());
+            let body = String::from_utf8_lossy(bytes);
+            match serde_json::from_str::<serde_json::Value>(&body) {
+                Ok(mut json) => {
+                    if let Some(redactor) = redactor {
+                        redactor.value(&mut json);
+                    }
+                    println!("{}", serde_json::to_string_pretty(&json).unwrap_or_default())
+                }
+                Err(_) => println!("{}", text(&body)),
+            }
+            resolved_text.push_str(&body);
+        }
+
+        if let Some(retry) = req.retry.filter(|&retry| retry > 0) {
+            let note = match allowed_retries(req) {
+                0 => format!("none ({} is not idempotent; set retry_non_idempotent to retry it)", request.method()),
+                _ => format!("up to {} time{}", retry, if retry == 1 { "" } else { "s" }),
+            };
+            println!("{} {}", "Retry:".bright_black(), note);
+        }
+
+        for (label, command) in [("Before hook:", &req.before), ("After hook:", &req.after)] {
+            if let Some(command) = command {
+                println!("{} {}", label.bright_black(), text(command));
+            }
+        }
+
+        let unresolved: Vec<&str> = ENV_VAR_REGEX
+            .find_iter(&resolved_text)
+            .map(|found| found.as_str())
+            .collect();
+        if !unresolved.is_empty() {
+            println!("{} {}", "⚠️  Unresolved variables:".yellow(), unresolved.join(", "));
+        }
+        println!("{}", "-".repeat(60).bright_black());
+    }
+}
+
+fn is_destructive(method: &str) -> bool {
+    method.eq_ignore_ascii_case("DELETE")
+}
+
+fn confirm_request(req: &RequestSpec) -> io::Result<bool> {
+    let mut stderr = io::stderr();
+    write!(
+        stderr,
+        "{} {} {} {} ",
+        "Confirm:".yellow().bold(),
+        req.method.to_uppercase().bright_yellow(),
+        req.url,
+        "[y/N]".bright_black()
+    )?;
+    stderr.flush()?;
+
+    let mut answer = String::new();
+    io::stdin().lock().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// `--file` value that reads the request file from stdin.
+const STDIN_FILE: &str = "-";
+
+/// Expands glob patterns among the `--file` values into the matching files, in order and without repeats.
+fn expand_files(patterns: &[String]) -> Result<Vec<String>, String> {
+    let mut files: Vec<String> = Vec::new();
+    for pattern in patterns {
+        let is_glob = pattern.contains(['*', '?', '[']);
+        if !is_glob {
+            if !files.contains(pattern) {
+                files.push(pattern.clone());
+            }
+            continue;
+        }
+        let entries = glob::glob(pattern).map_err(|err| format!("Invalid --file pattern '{}': {}", pattern, err))?;
+        let mut matched: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|path| path.is_file())
+            .map(|path| path.display().to_string())
+            .collect();
+        if matched.is_empty() {
+            return Err(format!("No files match --file pattern '{}'", pattern));
+        }
+        matched.sort();
+        for file in matched {
+            if !files.contains(&file) {
+                files.push(file);
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// The variables of environment `name` in the file's top-level `environments` object, or None when
+/// the file has no `environments`. Values are read after substituting the process environment into
+/// the file, so an environment can refer to secrets kept outside of it.
+fn environment_variables(substituted: &str, name: &str) -> Result<Option<HashMap<String, String>>, String> {
+    let value: serde_json::Value = serde_json::from_str(substituted).map_err(|err| json_error(substituted, &err))?;
+    let Some(environments) = value.get("environments") else {
+        return Ok(None);
+    };
+    let serde_json::Value::Object(environments) = environments else {
+        return Err("'environments' must be an object of environments".to_string());
+    };
+    let Some(environment) = environments.get(name) else {
+        let available: Vec<&str> = environments.keys().map(String::as_str).collect();
+        return Err(format!("Unknown environment '{}' (available: {})", name, available.join(", ")));
+    };
+    let serde_json::Value::Object(variables) = environment else {
+        return Err(format!("Environment '{}' must be an object of variables", name));
+    };
+    Ok(Some(variables.iter()
+        .map(|(variable, value)| match value {
+            serde_json::Value::String(text) => (variable.clone(), text.clone()),
+            other => (variable.clone(), other.to_string()),
+        })
+        .collect()))
+}
+
+/// Reads every request file and combines them into one suite, files in the order given.
+fn load_suite(patterns: &[String], environment: Option<&str>, relaxed_json: bool) -> Result<SuiteFile, Box<dyn std::error::Error>> {
+    let files = expand_files(patterns)?;
+    let mut suite = SuiteFile { requests: Vec::new(), invariants: Vec::new() };
+    let mut environment_found = false;
+    for file in &files {
+        let data = read_request_file(file).map_err(|err| format!("Failed to read '{}': {}", file, err))?;
+        // Placeholders are substituted first, so comments may mention them too.
+        let relaxed = relaxed_json || file.to_lowercase().ends_with(".jsonc");
+        let to_json = |text: String| if relaxed { jsonc::strip(&text) } else { text };
+        let mut substituted = to_json(substitute_env_vars(&data));
+        if let Some(name) = environment
+            && let Some(variables) = environment_variables(&substituted, name)
+                .map_err(|err| format!("Invalid request file '{}': {}", file, err))?
+        {
+            environment_found = true;
+            substituted = to_json(substitute_variables(&data, &variables));
+        }
+        let parsed = parse_suite(&substituted)
+            .map_err(|err| format!("Invalid request file '{}': {}", file, err))?;
+        for mut req in parsed.requests {
+            if files.len() > 1 {
+                req.source = Some(file.clone());
+            }
+            suite.requests.push(req);
+        }
+        suite.invariants.extend(parsed.invariants);
+    }
+    if let Some(name) = environment.filter(|_| !environment_found) {
+        return Err(format!("--env {}: no request file has an 'environments' object", name).into());
+    }
+    if suite.requests.is_empty() {
+        let files: Vec<String> = files.iter().map(|file| format!("'{}'", file)).collect();
+        return Err(format!("No requests found in {}", files.join(", ")).into());
+    }
+    Ok(suite)
+}
+
+fn read_request_file(path: &str) -> io::Result<String> {
+    if path == STDIN_FILE {
+        let mut data = String::new();
+        io::Read::read_to_string(&mut io::stdin(), &mut data)?;
+        return Ok(data);
+    }
+    fs::read_to_string(path)
+}
+
+/// Waits out a burst of change events (editors often write a file several times per save).
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(200);
+
+async fn watch(args: &Args, client: &reqwest::Client) -> Result<(), Box<dyn std::error::Error>> {
+    use notify::Watcher;
+
+    let names = expand_files(&args.file)?;
+    let files = names.iter()
+        .map(std::path::absolute)
+        .collect::<io::Result<Vec<_>>>()?;
+    let (sender, mut changes) = tokio::sync::mpsc::unbounded_channel();
+    let watched = files.clone();
+    let mut watcher = notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+        if let Ok(event) = event
+            && !event.kind.is_access()
+            && event.paths.iter().any(|path| watched.contains(path))
+        {
+            let _ = sender.send(());
+        }
+    })?;
+    // Watch the directories rather than the files, so saves that replace a file are seen too.
+    let mut dirs: Vec<&std::path::Path> = files.iter().filter_map(|file| file.parent()).collect();
+    dirs.sort();
+    dirs.dedup();
+    for dir in dirs {
+        watcher.watch(dir, notify::RecursiveMode::NonRecursive)?;
+    }
+    let watching = match names.as_slice() {
+        [name] => name.clone(),
+        _ => format!("{} files", names.len()),
+    };
+
+    loop {
+        print!("\x1B[2J\x1B[H");
+        tokio::select! {
+            result = run_suite(args, client) => {
+                if let Err(err) = result {
+                    eprintln!("{} {}", "❌ Error:".red().bold(), err);
+                }
+            }
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+
+        println!("\n{}", format!("Watching {} for changes (Ctrl-C to exit)", watching).bright_black());
+        tokio::select! {
+            _ = changes.recv() => {}
+            _ = tokio::signal::ctrl_c() => return Ok(()),
+        }
+        tokio::time::sleep(WATCH_DEBOUNCE).await;
+        while changes.try_recv().is_ok() {}
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    dotenvy::dotenv().ok();
+
+    let args = Args::parse();
+    if let Err(err) = run(&args).await {
+        eprintln!("{} {}", "❌ Error:".red().bold(), err);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: &Args) -> Result<(), Box<dyn std::error::Error>> {
+    if args.no_color {
+        colored::control::set_override(false);
+    }
+    let mut client_builder = reqwest::Client::builder()
         .timeout(Duration::from_secs(args.timeout))
-        .build()?;
+        .redirect(args.redirects.policy());
+    if let Some(connect_timeout) = args.connect_timeout {
+        client_builder = client_builder.connect_timeout(connect_timeout);
+    }
+    if args.no_keepalive {
+        client_builder = client_builder.pool_max_idle_per_host(0);
+    }
+    if let Some(user_agent) = &args.user_agent {
+        client_builder = client_builder.user_agent(user_agent);
+    }
+    if let Some(proxy_url) = &args.proxy {
+        let proxy = reqwest::Proxy::all(proxy_url)
+            .map_err(|err| format!("Invalid proxy URL '{}': {}", proxy_url, err))?;
+        client_builder = client_builder.proxy(proxy);
+    }
+    match args.http_version.as_str() {
+        "http1" => client_builder = client_builder.http1_only(),
+        "http2" => client_builder = client_builder.http2_prior_knowledge(),
+        _ => {}
+    }
+    if args.insecure {
+        eprintln!("{}", "⚠️  TLS certificate verification is disabled (--insecure). Do not use this against production.".yellow().bold());
+        client_builder = client_builder.danger_accept_invalid_certs(true);
+    }
+    if let Some(identity) = load_identity(args)? {
+        client_builder = client_builder.identity(identity);
+    }
+
+    if args.file.iter().any(|file| file == STDIN_FILE) && (args.record || args.watch) {
+        return Err("--record and --watch need a real --file, not stdin".into());
+    }
+
+    if args.record {
+        let [file] = args.file.as_slice() else {
+            return Err("--record writes to exactly one --file".into());
+        };
+        let output = std::path::Path::new(file);
+        if output.exists() {
+            return Err(format!("Refusing to overwrite existing file '{}' in --record mode", file).into());
+        }
+        let client = client_builder.redirect(reqwest::redirect::Policy::none()).build()?;
+        return record::run(&args.listen, output, client).await;
+    }
+    let client = client_builder.build()?;
+
+    if args.watch {
+        return watch(args, &client).await;
+    }
+    run_suite(args, &client).await
+}
 
-    if !output_json {
+async fn run_load(
+    args: &Args,
+    client: &reqwest::Client,
+    requests: &[RequestSpec],
+    options: Arc<RequestOptions>,
+    output: &str,
+    redactor: Option<&redact::Redactor>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let period = load::period(args.rps)?;
+    if !matches!(output, "pretty" | "json") {
+        return Err("--load only supports the pretty and json output formats".into());
+    }
+    if output == "pretty" {
         println!("{}", "=".repeat(60).bright_blue());
-        println!("{}", format!("HTTP Request Test Started (Timeout: {}s)", args.timeout).bright_blue().bold());
+        println!("{}", format!(
+            "Load Test Started ({} requests/s for {})",
+            args.rps, humantime::format_duration(args.duration)
+        ).bright_blue().bold());
         println!("{}", "=".repeat(60).bright_blue());
     }
 
-    let mut futures = FuturesUnordered::new();
+    let mut report = load::run(client, requests, options, args.rps, period, args.duration).await;
+    if let Some(redactor) = redactor {
+        report.errors = report.errors.into_iter().map(|(message, count)| (redactor.text(&message), count)).collect();
+    }
+
+    match output {
+        "json" if args.output_case == "camel" => {
+            let value = report::camel_case_keys(serde_json::to_value(&report)?);
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&report)?),
+        _ => print_load_report(&report),
+    }
+    Ok(())
+}
+
+fn print_load_report(report: &load::LoadReport) {
+    let error_rate = format!("{:.1}%", report.error_rate);
+    println!("\n{}", "Load test results".bright_white().bold());
+    println!("{} {} in {:.1}s ({:.1} requests/s, target {})",
+        "Requests:".bright_black(), report.total, report.elapsed_s, report.throughput_rps, report.target_rps);
+    println!("{} {} ({} failed, error rate {})",
+        "Success:".bright_black(), report.success, report.failed,
+        if report.failed == 0 { error_rate.green() } else { error_rate.red() });
+    println!("{} {} opened", "Connections:".bright_black(), report.connections_opened);
+
+    let name_width = report.requests.iter()
+        .map(|request| unicode_width::UnicodeWidthStr::width(request.name.as_str()))
+        .chain(std::iter::once("Request".len()))
+        .max()
+        .unwrap_or(0);
+    println!("\n{}", format!(
+        "{:<width$}  {:>7}  {:>7}  {:>9}  {:>9}  {:>9}  {:>9}  {:>9}",
+        "Request", "Total", "Failed", "p50 ms", "p90 ms", "p95 ms", "p99 ms", "max ms",
+        width = name_width
+    ).bright_black());
+    let row = |name: &str, total: usize, failed: usize, latency: &load::Latency| {
+        let padding = " ".repeat(name_width - unicode_width::UnicodeWidthStr::width(name));
+        let failed = format!("{:>7}", failed);
+        println!(
+            "{}{}  {:>7}  {}  {:>9.1}  {:>9.1}  {:>9.1}  {:>9.1}  {:>9.1}",
+            name, padding, total, if failed.trim() == "0" { failed.normal() } else { failed.red() },
+            latency.p50_ms, latency.p90_ms, latency.p95_ms, latency.p99_ms, latency.max_ms
+        );
+    };
+    for request in &report.requests {
+        row(&request.name, request.total, request.failed, &request.latency);
+    }
+    if report.requests.len() > 1 {
+        row("All", report.total, report.failed, &report.latency);
+    }
+
+    if !report.errors.is_empty() {
+        println!("\n{}", "Errors".bright_white().bold());
+        let mut errors: Vec<(&String, &usize)> = report.errors.iter().collect();
+        errors.sort_by(|a, b| b.1.cmp(a.1));
+        for (message, count) in errors {
+            println!("{} {}", format!("{:>7}x", count).red(), message.bright_black());
+        }
+    }
+}
+
+/// `--extract`: writes the body of the request called `name` to stdout, and nothing else. Other
+/// failed requests are reported on stderr; a failure of this request is an error.
+fn print_extracted(results: &[RequestResult], name: &str, selection: Option<&select::Path>) -> Result<(), Box<dyn std::error::Error>> {
+    for result in results.iter().filter(|result| result.name != name && !result.success && !result.skipped) {
+        eprintln!("{} {}: {}", "warning:".yellow(), result.name, report::failure_message(result));
+    }
+    let Some(result) = results.iter().rev().find(|result| result.name == name) else {
+        return Err(format!("Request '{}' was not run", name).into());
+    };
+    if !result.success {
+        return Err(format!("Request '{}' failed: {}", name, report::failure_message(result)).into());
+    }
+    let Some(selection) = selection else {
+        io::stdout().write_all(result.raw_body.as_deref().unwrap_or_default())?;
+        return Ok(());
+    };
+    let body = result.response_body.as_ref().ok_or_else(|| format!("Response of '{}' is not JSON, so --select cannot apply", name))?;
+    let matches = select::select(selection, body);
+    if matches.is_empty() {
+        return Err(format!("--select '{}' matched nothing in the response of '{}'", selection, name).into());
+    }
+    for value in matches {
+        // Strings are printed without quotes, so they can be assigned to shell variables directly.
+        match value {
+            serde_json::Value::String(text) => println!("{}", text),
+            other => println!("{}", other),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the request file once, printing its results in the selected output format.
+async fn run_suite(args: &Args, client: &reqwest::Client) -> Result<(), Box<dyn std::error::Error>> {
+    let SuiteFile { mut requests, invariants } = load_suite(&args.file, args.env.as_deref(), args.relaxed_json)?;
+    for req in &mut requests {
+        if req.method.is_empty() && req.graphql.is_some() {
+            req.method = "POST".to_string();
+        }
+    }
+    check_capture_names(&requests)?;
+    check_dependencies(&requests)?;
+    if !args.dry_run {
+        check_hooks(&requests, args)?;
+    }
+    let snapshots = args.snapshots || args.update_snapshots;
+    if snapshots {
+        check_snapshot_names(&requests)?;
+    }
+    let known_names: Vec<String> = requests.iter().filter_map(|req| req.name.clone()).collect();
+    let requests: Vec<RequestSpec> = requests
+        .into_iter()
+        .enumerate()
+        .filter(|(index, req)| is_selected(index + 1, req, args))
+        .map(|(_, req)| req)
+        .enumerate()
+        .map(|(index, req)| RequestSpec { position: index + 1, ..req })
+        .collect();
+
+    let output = args.output.as_deref().unwrap_or("pretty");
+    let pretty = output == "pretty";
+    let show_results = pretty && !args.quiet && args.extract.is_none();
+    if args.interactive && !(io::stdin().is_terminal() && io::stdout().is_terminal()) {
+        return Err("--interactive needs a terminal".into());
+    }
+    if let Some(name) = &args.extract
+        && !requests.iter().any(|req| req.name.as_deref().unwrap_or("Unnamed") == name)
+    {
+        return Err(format!("No request named '{}' to --extract", name).into());
+    }
+
+    let trace_header = match &args.trace_header {
+        Some(name) => Some(HeaderName::from_str(name)
+            .map_err(|_| format!("Invalid --trace-header name '{}'", name))?),
+        None => None,
+    };
+    let options = Arc::new(RequestOptions {
+        timeout: args.timeout,
+        connect_timeout: args.connect_timeout,
+        trace_header,
+        keep_exchange: (show_results && args.verbose > 0) || args.interactive,
+        connections: Default::default(),
+        default_headers: global_headers(args)?,
+        snapshot_dir: snapshots.then(|| std::path::PathBuf::from(&args.snapshot_dir)),
+        update_snapshots: args.update_snapshots,
+        max_response_bytes: args.max_response_bytes,
+        keep_raw_body: args.extract.clone(),
+    });
+
+    let redactor = args.redact.then(|| redact::Redactor::new(&args.redact_header)).transpose()?;
+
+    if args.dry_run {
+        print_dry_run(client, &requests, &options, redactor.as_ref());
+        return Ok(());
+    }
+
+    let interactive = io::stdin().is_terminal();
+    let mut skipped_results = Vec::new();
+    let mut to_send = Vec::new();
     for req in requests.clone() {
-        let client = client.clone();
-        futures.push(tokio::spawn(process_request(client, req, args.timeout)));
+        let needs_confirm = req.confirm.unwrap_or(args.confirm_destructive && is_destructive(&req.method));
+        if !needs_confirm || args.yes {
+            to_send.push(req);
+        } else if !interactive {
+            skipped_results.push(RequestResult::skipped(&req, "Confirmation required (pass --yes when stdin is not a TTY)"));
+        } else if confirm_request(&req)? {
+            to_send.push(req);
+        } else {
+            skipped_results.push(RequestResult::skipped(&req, "Not confirmed by user"));
+        }
+    }
+
+    if let Some(max_per_host) = args.max_per_host {
+        let mut per_host: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+        for req in &to_send {
+            *per_host.entry(host_of(&req.url)).or_insert(0) += 1;
+        }
+        if let Some((host, count)) = per_host.iter().find(|(_, count)| **count > max_per_host) {
+            return Err(format!(
+                "{} requests target host '{}', exceeding --max-per-host {}",
+                count, host, max_per_host
+            ).into());
+        }
+    }
+
+    if args.load {
+        return run_load(args, client, &to_send, options, output, redactor.as_ref()).await;
+    }
+
+    if show_results {
+        println!("{}", "=".repeat(60).bright_blue());
+        println!("{}", format!("HTTP Request Test Started (Timeout: {}s)", args.timeout).bright_blue().bold());
+        println!("{}", "=".repeat(60).bright_blue());
     }
 
     let mut results = Vec::new();
     let total_requests = requests.len();
-    let mut request_index = 0;
-    while let Some(result) = futures.next().await {
-        let result = result.unwrap();
-        request_index += 1;
-        if !output_json {
-            print_result(&result, total_requests, request_index);
+
+    let progress = (show_results && io::stdout().is_terminal()).then(|| {
+        let bar = indicatif::ProgressBar::new(total_requests as u64);
+        bar.set_style(indicatif::ProgressStyle::with_template("{bar:40.cyan/blue} {pos}/{len} {msg}")
+            .expect("valid progress template"));
+        bar
+    });
+    let mut failures = 0;
+    let started = std::time::Instant::now();
+    // With --ordered-output, results that finished before an earlier request wait here for their turn.
+    let mut held: BTreeMap<usize, (RequestResult, Option<Duration>)> = BTreeMap::new();
+    let mut next_position = 1;
+    let print = |result: &RequestResult, finished: Option<Duration>| {
+        // With --interactive the results are browsed afterwards instead.
+        if args.interactive {
+            return;
         }
+        let print = || print_result(result, total_requests, args.verbose > 1, args.select.as_ref(), finished);
+        match &progress {
+            Some(bar) => bar.suspend(print),
+            None => print(),
+        }
+    };
+    let mut show_result = |result: &RequestResult| {
+        if !show_results {
+            return;
+        }
+        let redacted;
+        let result = match &redactor {
+            Some(redactor) => {
+                let mut copy = result.clone();
+                redactor.result(&mut copy);
+                redacted = copy;
+                &redacted
+            }
+            None => result,
+        };
+        let finished = (!args.sequential).then(|| started.elapsed());
+        if args.ordered_output {
+            held.insert(result.position, (result.clone(), finished));
+            while let Some((result, finished)) = held.remove(&next_position) {
+                print(&result, finished);
+                next_position += 1;
+            }
+        } else {
+            print(result, finished);
+        }
+        let Some(bar) = &progress else { return };
+        if !result.success && !result.skipped {
+            failures += 1;
+            bar.set_message(format!("{} failed", failures).red().to_string());
+        }
+        bar.inc(1);
+    };
+    for result in skipped_results {
+        show_result(&result);
         results.push(result);
     }
 
+    if args.concurrency == Some(0) {
+        return Err("--concurrency must be at least 1".into());
+    }
+    if args.max_failures == Some(0) {
+        return Err("--max-failures must be at least 1".into());
+    }
+    let global_limit = args.concurrency.map(|max| Arc::new(Semaphore::new(max)));
+    let group_limits = group_limits(&to_send)?;
+
+    let delay = Duration::from_millis(args.delay_ms);
+    let deadline = args.max_duration.map(|max| tokio::time::Instant::now() + max);
+    let out_of_time = || format!("--max-duration of {} elapsed", humantime::format_duration(args.max_duration.unwrap_or_default()));
+    let mut aborted = None;
+    if args.sequential {
+        let mut outcomes: HashMap<String, bool> = results.iter().map(|result| (result.name.clone(), result.success)).collect();
+        let mut queue = dependency_order(to_send).into_iter().enumerate();
+        for (position, req) in queue.by_ref() {
+            // Dependencies come first, so every one that is part of the run has finished by now.
+            let immediate = match dependency_state(&req, &outcomes, &std::collections::HashSet::new()) {
+                DependencyState::Failed(reason) => Some(RequestResult::skipped(&req, &reason)),
+                _ => conditional_result(&req, &results),
+            };
+            if let Some(result) = immediate {
+                outcomes.insert(result.name.clone(), result.success);
+                show_result(&result);
+                results.push(result);
+                continue;
+            }
+            let current = req.clone();
+            let sent = within(deadline, async {
+                if position > 0 && !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+                process_with_hooks(client.clone(), req, options.clone()).await
+            }).await;
+            let Some(result) = sent else {
+                let reason = out_of_time();
+                results.push(RequestResult::skipped(&current, &format!("Not completed: run aborted ({})", reason)));
+                aborted = Some(reason);
+                break;
+            };
+            outcomes.insert(result.name.clone(), result.success);
+            show_result(&result);
+            aborted = abort_reason(&result, &results, args);
+            results.push(result);
+            if aborted.is_some() {
+                break;
+            }
+        }
+        if let Some(reason) = &aborted {
+            for (_, req) in queue {
+                results.push(RequestResult::skipped(&req, &format!("Not sent: run aborted ({})", reason)));
+            }
+        }
+    } else {
+        let mut outcomes: HashMap<String, bool> = results.iter().map(|result| (result.name.clone(), result.success)).collect();
+        let in_run: std::collections::HashSet<String> = outcomes.keys().cloned()
+            .chain(to_send.iter().filter_map(|req| req.name.clone()))
+            .collect();
+        let mut waiting: Vec<(usize, RequestSpec)> = to_send.into_iter().enumerate().collect();
+        let mut pending: HashMap<usize, RequestSpec> = HashMap::new();
+        let mut futures = FuturesUnordered::new();
+        loop {
+            // Start everything whose dependencies are done; skipping a request can unblock (and skip) others.
+            let mut progressed = true;
+            while progressed {
+                progressed = false;
+                for (position, req) in std::mem::take(&mut waiting) {
+                    let immediate = match dependency_state(&req, &outcomes, &in_run) {
+                        DependencyState::Waiting => {
+                            waiting.push((position, req));
+                            continue;
+                        }
+                        DependencyState::Failed(reason) => Some(RequestResult::skipped(&req, &reason)),
+                        DependencyState::Ready => conditional_result(&req, &results),
+                    };
+                    progressed = true;
+                    if let Some(result) = immediate {
+                        outcomes.insert(result.name.clone(), result.success);
+                        show_result(&result);
+                        results.push(result);
+                        continue;
+                    }
+                    pending.insert(position, req.clone());
+                    let client = client.clone();
+                    let options = options.clone();
+                    let mut limits = Vec::new();
+                    if let Some(limit) = req.group.as_ref().and_then(|group| group_limits.get(group)) {
+                        limits.push(limit.clone());
+                    }
+                    if let Some(limit) = &global_limit {
+                        limits.push(limit.clone());
+                    }
+                    // Dependent requests start as soon as their dependencies are done.
+                    let stagger = if req.depends_on.is_some() { Duration::ZERO } else { delay * position as u32 };
+                    futures.push(tokio::spawn(async move {
+                        tokio::time::sleep(stagger).await;
+                        (position, process_limited(client, req, options, limits).await)
+                    }));
+                }
+            }
+
+            let result = match within(deadline, futures.next()).await {
+                Some(Some(result)) => result,
+                Some(None) => break,
+                None => {
+                    aborted = Some(out_of_time());
+                    break;
+                }
+            };
+            let (position, result) = result.unwrap();
+            pending.remove(&position);
+            outcomes.insert(result.name.clone(), result.success);
+            show_result(&result);
+            aborted = abort_reason(&result, &results, args);
+            results.push(result);
+            if aborted.is_some() {
+                break;
+            }
+        }
+        if let Some(reason) = &aborted {
+            for handle in futures.iter() {
+                handle.abort();
+            }
+            let mut remaining: Vec<(usize, RequestSpec)> = pending.into_iter().collect();
+            remaining.sort_by_key(|(position, _)| *position);
+            for (_, req) in remaining {
+                results.push(RequestResult::skipped(&req, &format!("Not completed: run aborted ({})", reason)));
+            }
+            for (_, req) in waiting {
+                results.push(RequestResult::skipped(&req, &format!("Not sent: run aborted ({})", reason)));
+            }
+        }
+    }
+
+    // Requests cut off by an abort never reached `show_result`, so later ones may still be held.
+    for (result, finished) in held.values() {
+        print(result, *finished);
+    }
+    if let Some(bar) = &progress {
+        bar.finish_and_clear();
+    }
+
     let success_count = results.iter().filter(|r| r.success).count();
-    let fail_count = results.len() - success_count;
-    let success_rate = if !requests.is_empty() {
-        (success_count as f64 / requests.len() as f64) * 100.0
+    let skipped_count = results.iter().filter(|r| r.skipped).count();
+    let fail_count = results.len() - success_count - skipped_count;
+    let executed = results.len() - skipped_count;
+    let success_rate = if executed > 0 {
+        (success_count as f64 / executed as f64) * 100.0
     } else {
         0.0
     };
 
-    let failed_requests: Vec<String> = results
-        .iter()
-        .filter(|r| !r.success)
-        .map(|r| r.name.clone())
-        .collect();
+    let drift = match &args.hash_state {
+        Some(path) => Some(drift::check_drift(std::path::Path::new(path), &results, &known_names)?),
+        None => None,
+    };
 
-    if output_json {
-        let summary = TestSummary {
-            total: requests.len(),
-            success: success_count,
-            failed: fail_count,
-            success_rate,
-            results,
-        };
-        println!("{}", serde_json::to_string_pretty(&summary)?);
-    } else {
-        print_summary_box(requests.len(), success_count, fail_count, success_rate, failed_requests);
+    let mut summary = TestSummary {
+        total: requests.len(),
+        success: success_count,
+        failed: fail_count,
+        skipped: skipped_count,
+        success_rate,
+        aborted,
+        hosts: summarize_hosts(&results),
+        status_classes: summarize_status_classes(&results),
+        methods: summarize_methods(&results),
+        slowest: slowest_request(&results),
+        total_bytes: results.iter().map(|result| result.body_bytes).sum(),
+        drift,
+        invariants: check_invariants(&invariants, &results),
+        results,
+    };
+    if let Some(redactor) = &redactor {
+        summary.results.iter_mut().for_each(|result| redactor.result(result));
+        // The slowest request repeats a result's URL, so it is taken again from the masked results.
+        summary.slowest = slowest_request(&summary.results);
+        for outcome in &mut summary.invariants {
+            outcome.message = redactor.text(&outcome.message);
+        }
+    }
+
+    if args.interactive {
+        tui::browse(&summary.results, args.verbose > 1)?;
+    }
+    if let Some(name) = &args.extract {
+        return print_extracted(&summary.results, name, args.select.as_ref());
+    }
+
+    match output {
+        "json" if args.output_case == "camel" => {
+            let value = report::camel_case_keys(serde_json::to_value(&summary)?);
+            println!("{}", serde_json::to_string_pretty(&value)?);
+        }
+        "json" => println!("{}", serde_json::to_string_pretty(&summary)?),
+        "junit" => print!("{}", report::junit_xml(&summary)),
+        "prometheus" => print!("{}", report::prometheus(&summary)),
+        "allure" => {
+            let written = report::write_allure_results(&summary, std::path::Path::new(&args.allure_dir))?;
+            println!("Wrote {} Allure results to {}", written, args.allure_dir);
+        }
+        _ => {
+            print_summary_box(&summary, args.quiet);
+            if summary.hosts.len() > 1 {
+                print_host_table(&summary.hosts);
+            }
+            if !summary.invariants.is_empty() {
+                print_invariants(&summary.invariants);
+            }
+            if let Some(drift) = &summary.drift {
+                print_drift_report(drift);
+            }
+        }
     }
 
     Ok(())
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn spec(value: serde_json::Value) -> RequestSpec {
+        serde_json::from_value(value).expect("valid request")
+    }
+
+    fn options() -> RequestOptions {
+        RequestOptions {
+            timeout: 5,
+            connect_timeout: None,
+            trace_header: None,
+            keep_exchange: false,
+            connections: Default::default(),
+            default_headers: HashMap::new(),
+            snapshot_dir: None,
+            update_snapshots: false,
+            max_response_bytes: None,
+            keep_raw_body: None,
+        }
+    }
+
+    #[test]
+    fn invalid_header_name_and_value_fail_the_request() {
+        let req = spec(json!({
+            "url": "http://localhost/",
+            "method": "GET",
+            "headers": { "X Bad": "a", "X-Ok": "line\nbreak" }
+        }));
+        let err = prepare_request(&reqwest::Client::new(), &req, &options()).err().expect("headers are invalid");
+        assert_eq!(err, r#"Invalid headers: invalid header name "X Bad"; invalid value for header 'X-Ok': "line\nbreak""#);
+    }
+
+    #[test]
+    fn body_patch_merges_objects_and_replaces_everything_else() {
+        let req = spec(json!({
+            "url": "http://localhost/",
+            "body_base": { "user": { "name": "a", "role": "admin" }, "tags": ["x", "y"], "note": "keep", "extra": 1 },
+            "body_patch": { "user": { "name": "b" }, "tags": ["z"], "extra": null }
+        }));
+        let body = effective_body(&req).unwrap();
+        assert_eq!(body, Some(json!({ "user": { "name": "b", "role": "admin" }, "tags": ["z"], "note": "keep", "extra": null })));
+    }
+
+    #[test]
+    fn graphql_requires_post() {
+        let req = spec(json!({ "url": "http://localhost/", "method": "GET", "graphql": { "query": "{ me { id } }" } }));
+        let err = prepare_request(&reqwest::Client::new(), &req, &options()).err().expect("GET cannot carry graphql");
+        assert_eq!(err, "'graphql' is sent as a JSON body, so it requires method POST, not GET.");
+        let req = spec(json!({ "url": "http://localhost/", "method": "post", "graphql": { "query": "{ me { id } }" } }));
+        assert!(prepare_request(&reqwest::Client::new(), &req, &options()).is_ok());
+    }
+
+    #[test]
+    fn body_with_body_base_is_rejected() {
+        let req = spec(json!({ "url": "http://localhost/", "body": {}, "body_base": {} }));
+        assert_eq!(effective_body(&req), Err("Cannot use 'body' and 'body_base' fields simultaneously.".to_string()));
+        let req = spec(json!({ "url": "http://localhost/", "body_patch": {} }));
+        assert_eq!(effective_body(&req), Err("'body_patch' requires 'body_base'.".to_string()));
+    }
+
+    /// Serves `response` (status line, headers, and body) to the first connection on a local port.
+    async fn serve_once(response: Vec<u8>) -> String {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = [0; 4096];
+            let _ = socket.read(&mut request).await;
+            socket.write_all(&response).await.unwrap();
+        });
+        url
+    }
+
+    #[tokio::test]
+    async fn gzip_response_is_decoded_into_json() {
+        use flate2::{write::GzEncoder, Compression};
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        io::Write::write_all(&mut encoder, br#"{"id": 1, "name": "gzip"}"#).unwrap();
+        let body = encoder.finish().unwrap();
+        let mut response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        ).into_bytes();
+        response.extend(body);
+        let url = serve_once(response).await;
+
+        let req = spec(json!({ "name": "gzip", "url": url, "method": "GET" }));
+        let result = process_request(reqwest::Client::new(), req, Arc::new(options())).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.response_body, Some(json!({ "id": 1, "name": "gzip" })));
+    }
+
+    async fn connect_error(url: &str) -> reqwest::Error {
+        reqwest::Client::new().get(url).send().await.expect_err("the request cannot connect")
+    }
+
+    #[tokio::test]
+    async fn unresolvable_host_is_a_dns_error() {
+        let err = connect_error("http://jhttp-test.invalid/").await;
+        assert_eq!(connect_failure(&err), (ErrorKind::Dns, "Could not resolve host"));
+    }
+
+    #[tokio::test]
+    async fn refused_port_is_a_connect_error() {
+        let err = connect_error("http://127.0.0.1:1/").await;
+        assert_eq!(connect_failure(&err), (ErrorKind::Connect, "Connection refused"));
+    }
+
+    #[test]
+    fn repeated_form_field_is_sent_once_per_value() {
+        let req = spec(json!({ "url": "http://localhost/", "method": "POST", "form": { "tags[]": ["a", "b"] } }));
+        let Ok(prepared) = prepare_request(&reqwest::Client::new(), &req, &options()) else {
+            panic!("the request is valid");
+        };
+        let request = prepared.builder.build().unwrap();
+        let body = request.body().and_then(|body| body.as_bytes()).unwrap();
+        assert_eq!(body, b"tags%5B%5D=a&tags%5B%5D=b");
+    }
+
+    #[test]
+    fn empty_request_file_is_reported() {
+        assert_eq!(
+            parse_suite(" \n").err(),
+            Some("the file is empty; expected a JSON array of requests or an object with \"requests\"".to_string())
+        );
+    }
+
+    #[test]
+    fn request_file_without_requests_is_reported() {
+        let path = env::temp_dir().join(format!("jhttp-test-{}.json", std::process::id()));
+        fs::write(&path, "[]").unwrap();
+        let path = path.display().to_string();
+        let err = load_suite(std::slice::from_ref(&path), None, false).err().map(|err| err.to_string());
+        fs::remove_file(&path).unwrap();
+        assert_eq!(err, Some(format!("No requests found in '{}'", path)));
+    }
+
+    #[test]
+    fn json_error_points_at_the_trailing_comma() {
+        let text = "[\n  {\"url\": \"http://localhost/\"},\n]";
+        assert_eq!(
+            parse_suite(text).err(),
+            Some("trailing comma at line 3 column 1\n  3 | ]\n    | ^".to_string())
+        );
+    }
+
+    #[test]
+    fn environment_error_points_at_the_trailing_comma() {
+        let text = "{\n  \"environments\": {\"dev\": {}},\n}";
+        assert_eq!(
+            environment_variables(text, "dev").err(),
+            Some("trailing comma at line 3 column 1\n  3 | }\n    | ^".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn server_error_with_a_failed_assertion_is_a_status_error() {
+        let body = r#"{"state": "down"}"#;
+        let response = format!(
+            "HTTP/1.1 503 Service Unavailable\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let url = serve_once(response.into_bytes()).await;
+
+        let req = spec(json!({ "name": "503", "url": url, "method": "GET", "assert": { "/state": "up" } }));
+        let result = process_request(reqwest::Client::new(), req, Arc::new(options())).await;
+        assert!(!result.success);
+        assert_eq!(result.error_kind, Some(ErrorKind::Status));
+        assert!(result.error.as_deref().is_some_and(|error| error.starts_with("Assertion failed")), "{:?}", result.error);
+        assert!(is_retryable(&result));
+    }
+}
@@ -0,0 +1,318 @@
+use colored::*;
+use serde_json::{json, Map, Value};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Headers that only describe the client-proxy hop and must not be forwarded or recorded.
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "proxy-connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+    "host",
+    "content-length",
+];
+
+struct ProxiedRequest {
+    method: String,
+    url: reqwest::Url,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+struct Recorder {
+    output: PathBuf,
+    requests: Mutex<Vec<Value>>,
+}
+
+impl Recorder {
+    fn push(&self, spec: Value) -> std::io::Result<usize> {
+        let mut requests = self.requests.lock().unwrap();
+        requests.push(spec);
+        std::fs::write(&self.output, serde_json::to_string_pretty(&*requests)?)?;
+        Ok(requests.len())
+    }
+}
+
+fn find_head_end(buffer: &[u8]) -> Option<usize> {
+    buffer.windows(4).position(|window| window == b"\r\n\r\n").map(|pos| pos + 4)
+}
+
+async fn read_chunked(stream: &mut TcpStream, mut pending: Vec<u8>) -> Result<Vec<u8>, String> {
+    let mut body = Vec::new();
+    loop {
+        let line_end = loop {
+            if let Some(pos) = pending.windows(2).position(|window| window == b"\r\n") {
+                break pos;
+            }
+            read_more(stream, &mut pending).await?;
+        };
+        let size_line = String::from_utf8_lossy(&pending[..line_end]).to_string();
+        let size_text = size_line.split(';').next().unwrap_or("").trim();
+        let size = usize::from_str_radix(size_text, 16)
+            .map_err(|_| format!("Invalid chunk size '{}'", size_text))?;
+        pending.drain(..line_end + 2);
+
+        if size == 0 {
+            return Ok(body);
+        }
+        while pending.len() < size + 2 {
+            read_more(stream, &mut pending).await?;
+        }
+        body.extend_from_slice(&pending[..size]);
+        pending.drain(..size + 2);
+    }
+}
+
+async fn read_more(stream: &mut TcpStream, buffer: &mut Vec<u8>) -> Result<(), String> {
+    let mut chunk = [0u8; 8192];
+    let read = stream.read(&mut chunk).await.map_err(|err| err.to_string())?;
+    if read == 0 {
+        return Err("Connection closed before the request was complete".to_string());
+    }
+    buffer.extend_from_slice(&chunk[..read]);
+    Ok(())
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<ProxiedRequest, (u16, String)> {
+    let bad_request = |message: String| (400, message);
+
+    let mut buffer = Vec::new();
+    let head_end = loop {
+        if let Some(end) = find_head_end(&buffer) {
+            break end;
+        }
+        if buffer.len() > MAX_HEAD_BYTES {
+            return Err(bad_request("Request head too large".to_string()));
+        }
+        read_more(stream, &mut buffer).await.map_err(bad_request)?;
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..head_end]).to_string();
+    let mut lines = head.split("\r\n");
+    let request_line = lines.next().unwrap_or("");
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or("").to_uppercase();
+    let target = parts.next().unwrap_or("").to_string();
+
+    if method == "CONNECT" {
+        return Err((501, "HTTPS (CONNECT) interception is not supported; record over plain HTTP".to_string()));
+    }
+
+    let headers: Vec<(String, String)> = lines
+        .filter(|line| !line.is_empty())
+        .filter_map(|line| line.split_once(':'))
+        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+        .collect();
+    let header = |name: &str| {
+        headers.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    };
+
+    // Absolute-form targets come from clients configured to use us as a proxy;
+    // origin-form targets come from clients pointed at us directly and use the Host header.
+    let url = if target.starts_with('/') {
+        let host = header("host").ok_or_else(|| bad_request("Missing Host header".to_string()))?;
+        format!("http://{}{}", host, target)
+    } else {
+        target
+    };
+    let url = reqwest::Url::parse(&url).map_err(|err| bad_request(format!("Invalid request target: {}", err)))?;
+
+    let pending = buffer[head_end..].to_vec();
+    let chunked = header("transfer-encoding").is_some_and(|value| value.to_lowercase().contains("chunked"));
+    let body = if chunked {
+        read_chunked(stream, pending).await.map_err(bad_request)?
+    } else {
+        let length: usize = header("content-length")
+            .map(|value| value.parse().map_err(|_| bad_request("Invalid Content-Length".to_string())))
+            .transpose()?
+            .unwrap_or(0);
+        let mut body = pending;
+        while body.len() < length {
+            read_more(stream, &mut body).await.map_err(bad_request)?;
+        }
+        body.truncate(length);
+        body
+    };
+
+    let headers = headers
+        .into_iter()
+        .filter(|(name, _)| !HOP_BY_HOP.contains(&name.to_lowercase().as_str()))
+        .collect();
+
+    Ok(ProxiedRequest { method, url, headers, body })
+}
+
+/// Inserts `key: value`, turning the entry into a list when the key repeats.
+fn insert_repeated(map: &mut Map<String, Value>, key: &str, value: &str) {
+    match map.get_mut(key) {
+        Some(Value::Array(values)) => values.push(json!(value)),
+        Some(existing) => *existing = json!([existing.clone(), value]),
+        None => {
+            map.insert(key.to_string(), json!(value));
+        }
+    }
+}
+
+fn to_spec(request: &ProxiedRequest) -> (Value, Option<String>) {
+    let mut spec = Map::new();
+    let mut warning = None;
+
+    spec.insert("name".to_string(), json!(format!("{} {}", request.method, request.url.path())));
+    let mut url = request.url.clone();
+    url.set_query(None);
+    spec.insert("url".to_string(), json!(url.to_string()));
+    spec.insert("method".to_string(), json!(request.method));
+
+    let mut params: Map<String, Value> = Map::new();
+    for (key, value) in request.url.query_pairs() {
+        insert_repeated(&mut params, &key, &value);
+    }
+    if !params.is_empty() {
+        spec.insert("params".to_string(), Value::Object(params));
+    }
+
+    let content_type = request.headers.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case("content-type"))
+        .map(|(_, value)| value.to_lowercase())
+        .unwrap_or_default();
+
+    let mut headers: Map<String, Value> = Map::new();
+    for (name, value) in &request.headers {
+        insert_repeated(&mut headers, name, value);
+    }
+
+    if !request.body.is_empty() {
+        if content_type.starts_with("application/x-www-form-urlencoded") {
+            let pairs: Vec<(String, String)> = serde_urlencoded::from_bytes(&request.body).unwrap_or_default();
+            let mut form: Map<String, Value> = Map::new();
+            for (key, value) in &pairs {
+                insert_repeated(&mut form, key, value);
+            }
+            spec.insert("form".to_string(), Value::Object(form));
+            headers.retain(|name, _| !name.eq_ignore_ascii_case("content-type"));
+        } else if let Ok(body) = serde_json::from_slice::<Value>(&request.body) {
+            spec.insert("body".to_string(), body);
+        } else {
+            warning = Some(format!(
+                "{} {}: {} byte non-JSON body was not recorded",
+                request.method, request.url, request.body.len()
+            ));
+        }
+    }
+
+    if !headers.is_empty() {
+        spec.insert("headers".to_string(), Value::Object(headers));
+    }
+
+    (Value::Object(spec), warning)
+}
+
+async fn write_response(stream: &mut TcpStream, status: u16, reason: &str, headers: &[(String, String)], body: &[u8]) -> std::io::Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str(&format!("Content-Length: {}\r\nConnection: close\r\n\r\n", body.len()));
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await
+}
+
+async fn forward(client: &reqwest::Client, request: &ProxiedRequest) -> Result<(u16, String, Vec<(String, String)>, Vec<u8>), String> {
+    let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|err| err.to_string())?;
+    let mut builder = client.request(method, request.url.clone());
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+    if !request.body.is_empty() {
+        builder = builder.body(request.body.clone());
+    }
+
+    let response = builder.send().await.map_err(|err| crate::error_chain(&err))?;
+    let status = response.status();
+    let headers = response.headers().iter()
+        .filter(|(name, _)| !HOP_BY_HOP.contains(&name.as_str()))
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).to_string()))
+        .collect();
+    let body = response.bytes().await.map_err(|err| crate::error_chain(&err))?;
+    Ok((status.as_u16(), status.canonical_reason().unwrap_or("").to_string(), headers, body.to_vec()))
+}
+
+async fn handle_connection(mut stream: TcpStream, client: reqwest::Client, recorder: Arc<Recorder>) {
+    let request = match read_request(&mut stream).await {
+        Ok(request) => request,
+        Err((status, message)) => {
+            eprintln!("{} {}", "⚠️  Rejected:".yellow(), message);
+            let reason = if status == 501 { "Not Implemented" } else { "Bad Request" };
+            let _ = write_response(&mut stream, status, reason, &[], message.as_bytes()).await;
+            return;
+        }
+    };
+
+    match forward(&client, &request).await {
+        Ok((status, reason, headers, body)) => {
+            let (spec, warning) = to_spec(&request);
+            match recorder.push(spec) {
+                Ok(count) => println!(
+                    "{} {} {} {}",
+                    format!("[{}]", count).bright_cyan(),
+                    request.method.bright_yellow(),
+                    request.url,
+                    format!("→ {}", status).bright_black()
+                ),
+                Err(err) => eprintln!("{} {}", "❌ Failed to write recording:".red(), err),
+            }
+            if let Some(warning) = warning {
+                eprintln!("{} {}", "⚠️ ".yellow(), warning);
+            }
+            let _ = write_response(&mut stream, status, &reason, &headers, &body).await;
+        }
+        Err(err) => {
+            eprintln!("{} {} {}: {}", "❌ Upstream error:".red(), request.method, request.url, err);
+            let _ = write_response(&mut stream, 502, "Bad Gateway", &[], err.as_bytes()).await;
+        }
+    }
+}
+
+/// Runs a plain-HTTP forwarding proxy and records every request that passes through it
+/// as a request spec in `output`, rewriting the file after each capture.
+pub async fn run(listen: &str, output: &Path, client: reqwest::Client) -> Result<(), Box<dyn std::error::Error>> {
+    let listener = TcpListener::bind(listen).await
+        .map_err(|err| format!("Failed to listen on {}: {}", listen, err))?;
+    let recorder = Arc::new(Recorder {
+        output: output.to_path_buf(),
+        requests: Mutex::new(Vec::new()),
+    });
+
+    println!("{}", "=".repeat(60).bright_blue());
+    println!("{}", format!("Recording proxy listening on http://{}", listen).bright_blue().bold());
+    println!("{}", format!("Captured requests are written to {}", output.display()).bright_blue());
+    println!("{}", "Press Ctrl-C to stop".bright_blue());
+    println!("{}", "=".repeat(60).bright_blue());
+
+    loop {
+        tokio::select! {
+            accepted = listener.accept() => {
+                let (stream, _) = accepted?;
+                tokio::spawn(handle_connection(stream, client.clone(), recorder.clone()));
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    let count = recorder.requests.lock().unwrap().len();
+    println!("\n{}", format!("Recorded {} requests to {}", count, output.display()).green().bold());
+    Ok(())
+}
@@ -0,0 +1,167 @@
+//! `--redact`: masks secrets in everything that is printed or written as a report. Requests are
+//! always sent with the real values.
+
+use crate::RequestResult;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+const MASK: &str = "***";
+
+/// Headers that are always masked.
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "proxy-authorization", "cookie", "set-cookie", "x-api-key"];
+
+/// Environment variables and JSON fields whose name matches this hold secrets.
+static SECRET_NAME: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"(?i)(token|secret|passw(or)?d|api[_-]?key|credential)").unwrap());
+
+/// Values shorter than this are not masked inside other text, since they would match too often.
+const MIN_SECRET_LEN: usize = 4;
+
+pub struct Redactor {
+    header_patterns: Vec<Regex>,
+    /// Values of secret environment variables, longest first so overlapping values are fully masked.
+    secrets: Vec<String>,
+}
+
+impl Redactor {
+    /// `header_patterns` are extra (case-insensitive) regexes; a header whose name matches one is masked.
+    pub fn new(header_patterns: &[String]) -> Result<Redactor, String> {
+        let header_patterns = header_patterns.iter()
+            .map(|pattern| Regex::new(&format!("(?i){}", pattern))
+                .map_err(|err| format!("Invalid --redact-header pattern '{}': {}", pattern, err)))
+            .collect::<Result<_, _>>()?;
+        let mut secrets: Vec<String> = std::env::vars()
+            .filter(|(name, value)| SECRET_NAME.is_match(name) && value.len() >= MIN_SECRET_LEN)
+            .map(|(_, value)| value)
+            .collect();
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        secrets.dedup();
+        Ok(Redactor { header_patterns, secrets })
+    }
+
+    pub fn is_sensitive_header(&self, name: &str) -> bool {
+        SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str())
+            || self.header_patterns.iter().any(|pattern| pattern.is_match(name))
+    }
+
+    pub fn header(&self, name: &str, value: &str) -> String {
+        if self.is_sensitive_header(name) { MASK.to_string() } else { self.text(value) }
+    }
+
+    /// Masks every occurrence of a secret environment variable's value.
+    pub fn text(&self, text: &str) -> String {
+        let mut masked = text.to_string();
+        for secret in &self.secrets {
+            if masked.contains(secret.as_str()) {
+                masked = masked.replace(secret.as_str(), MASK);
+            }
+        }
+        masked
+    }
+
+    /// Masks secrets in strings, and the whole value of fields named like a secret or a sensitive header.
+    pub fn value(&self, value: &mut Value) {
+        match value {
+            Value::String(text) => *text = self.text(text),
+            Value::Array(items) => items.iter_mut().for_each(|item| self.value(item)),
+            Value::Object(map) => {
+                for (key, child) in map.iter_mut() {
+                    if (SECRET_NAME.is_match(key) || self.is_sensitive_header(key)) && !child.is_null() {
+                        *child = Value::String(MASK.to_string());
+                    } else {
+                        self.value(child);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub fn result(&self, result: &mut RequestResult) {
+        result.url = self.text(&result.url);
+        result.error = result.error.as_deref().map(|error| self.text(error));
+        for body in [&mut result.response_body, &mut result.request_body].into_iter().flatten() {
+            self.value(body);
+        }
+        for (name, captured) in result.captures.iter_mut() {
+            if SECRET_NAME.is_match(name) {
+                *captured = Value::String(MASK.to_string());
+            } else {
+                self.value(captured);
+            }
+        }
+        for outcome in &mut result.assertions {
+            outcome.message = self.text(&outcome.message);
+        }
+        if let Some(snapshot) = &mut result.snapshot {
+            for difference in &mut snapshot.differences {
+                // The values of a secret field are masked whole, as they would be inside their object.
+                let field = difference.path.rsplit('/').next().unwrap_or("");
+                let secret = SECRET_NAME.is_match(field) || self.is_sensitive_header(field);
+                for value in [&mut difference.expected, &mut difference.actual].into_iter().flatten() {
+                    if secret && !value.is_null() {
+                        *value = Value::String(MASK.to_string());
+                    } else {
+                        self.value(value);
+                    }
+                }
+            }
+        }
+        if let Some(exchange) = &mut result.exchange {
+            exchange.request_line = self.text(&exchange.request_line);
+            for (name, value) in exchange.request_headers.iter_mut().chain(exchange.response_headers.iter_mut()) {
+                *value = self.header(name, value);
+            }
+            for text in [&mut exchange.request_body, &mut exchange.response_text].into_iter().flatten() {
+                *text = self.redact_body_text(text);
+            }
+        }
+    }
+
+    /// Like `text`, but a JSON body also gets its secret-looking fields masked.
+    fn redact_body_text(&self, text: &str) -> String {
+        match serde_json::from_str::<Value>(text) {
+            Ok(mut json) if json.is_object() || json.is_array() => {
+                self.value(&mut json);
+                serde_json::to_string(&json).unwrap_or_default()
+            }
+            _ => self.text(text),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Difference;
+    use crate::snapshot::{SnapshotResult, SnapshotStatus};
+    use serde_json::json;
+
+    #[test]
+    fn snapshot_differences_are_masked() {
+        let redactor = Redactor { header_patterns: Vec::new(), secrets: vec!["supersecretvalue".to_string()] };
+        let req = serde_json::from_value(json!({ "url": "http://localhost/" })).unwrap();
+        let mut result = RequestResult {
+            snapshot: Some(SnapshotResult {
+                path: "snapshots/r.json".to_string(),
+                status: SnapshotStatus::Mismatch,
+                differences: vec![
+                    Difference {
+                        path: "/note".to_string(),
+                        expected: Some(json!("key supersecretvalue")),
+                        actual: Some(json!({ "password": "hunter22", "id": 1 })),
+                    },
+                    Difference { path: "/access_token".to_string(), expected: Some(json!("old")), actual: Some(json!("new")) },
+                ],
+            }),
+            ..RequestResult::new(&req)
+        };
+        redactor.result(&mut result);
+        let differences = &result.snapshot.unwrap().differences;
+        assert_eq!(differences[0].expected, Some(json!("key ***")));
+        assert_eq!(differences[0].actual, Some(json!({ "password": "***", "id": 1 })));
+        assert_eq!(differences[1].expected, Some(json!("***")));
+        assert_eq!(differences[1].actual, Some(json!("***")));
+    }
+}
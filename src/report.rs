@@ -0,0 +1,279 @@
+use crate::{RequestResult, TestSummary};
+use serde_json::json;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            c if c.is_control() && c != '\n' && c != '\t' && c != '\r' => {}
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+pub fn failure_message(result: &RequestResult) -> String {
+    if let Some(error) = &result.error {
+        return error.clone();
+    }
+    match result.status_code {
+        Some(code) => format!(
+            "Unexpected status: {} {}",
+            code,
+            result.status_text.as_deref().unwrap_or("")
+        ).trim_end().to_string(),
+        None => "Request failed".to_string(),
+    }
+}
+
+pub fn junit_xml(summary: &TestSummary) -> String {
+    let total_secs: f64 = summary.results.iter().map(|r| r.response_time_ms / 1000.0).sum();
+
+    let broken_invariants = summary.invariants.iter().filter(|outcome| !outcome.passed).count();
+
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.push_str(&format!(
+        "<testsuite name=\"jhttp\" tests=\"{}\" failures=\"{}\" errors=\"0\" skipped=\"{}\" time=\"{:.3}\">\n",
+        summary.total + summary.invariants.len(),
+        summary.failed + broken_invariants,
+        summary.skipped,
+        total_secs
+    ));
+
+    for result in &summary.results {
+        let case = format!(
+            "  <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\"",
+            escape_xml(&result.name),
+            escape_xml(&result.method.to_uppercase()),
+            result.response_time_ms / 1000.0
+        );
+        if result.skipped {
+            xml.push_str(&format!(
+                "{}>\n    <skipped message=\"{}\"/>\n  </testcase>\n",
+                case,
+                escape_xml(result.error.as_deref().unwrap_or(""))
+            ));
+        } else if !result.success {
+            let message = escape_xml(&failure_message(result));
+            xml.push_str(&format!(
+                "{}>\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                case, message, message
+            ));
+        } else {
+            xml.push_str(&format!("{}/>\n", case));
+        }
+    }
+
+    for outcome in &summary.invariants {
+        let case = format!("  <testcase name=\"{}\" classname=\"invariant\" time=\"0.000\"", escape_xml(&outcome.name));
+        if outcome.passed {
+            xml.push_str(&format!("{}/>\n", case));
+        } else {
+            let message = escape_xml(&outcome.message);
+            xml.push_str(&format!(
+                "{}>\n    <failure message=\"{}\">{}</failure>\n  </testcase>\n",
+                case, message, message
+            ));
+        }
+    }
+
+    xml.push_str("</testsuite>\n");
+    xml
+}
+
+fn escape_label(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n")
+}
+
+/// The run in the Prometheus text exposition format. Skipped requests only show up in `jhttp_requests`.
+pub fn prometheus(summary: &TestSummary) -> String {
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, String)>| {
+        text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+        for (labels, value) in samples {
+            text.push_str(&format!("{}{{{}}} {}\n", name, labels, value));
+        }
+    };
+    let sent: Vec<&RequestResult> = summary.results.iter().filter(|result| !result.skipped).collect();
+
+    metric(
+        "jhttp_request_duration_ms",
+        "gauge",
+        "Response time of the request in milliseconds.",
+        sent.iter()
+            .map(|result| {
+                let status = result.status_code.map(|code| code.to_string()).unwrap_or_default();
+                let labels = format!(
+                    "name=\"{}\",method=\"{}\",status=\"{}\"",
+                    escape_label(&result.name),
+                    escape_label(&result.method.to_uppercase()),
+                    status
+                );
+                (labels, format!("{:.3}", result.response_time_ms))
+            })
+            .collect(),
+    );
+    metric(
+        "jhttp_request_success",
+        "gauge",
+        "Whether the request succeeded (1) or failed (0).",
+        sent.iter()
+            .map(|result| (format!("name=\"{}\"", escape_label(&result.name)), (result.success as u8).to_string()))
+            .collect(),
+    );
+    metric(
+        "jhttp_requests",
+        "gauge",
+        "Number of requests in the run by outcome.",
+        [("success", summary.success), ("failed", summary.failed), ("skipped", summary.skipped)]
+            .into_iter()
+            .map(|(outcome, count)| (format!("result=\"{}\"", outcome), count.to_string()))
+            .collect(),
+    );
+    text
+}
+
+/// Fields holding data received from the server (or named by the user); their contents are left untouched.
+/// `errors` is the load report's count per failure message; `expected` and `actual` are the values of a
+/// snapshot difference.
+const PAYLOAD_FIELDS: &[&str] = &["response_body", "captures", "errors", "expected", "actual"];
+
+fn camel_case(key: &str) -> String {
+    let mut converted = String::with_capacity(key.len());
+    let mut upper_next = false;
+    for c in key.chars() {
+        if c == '_' {
+            upper_next = true;
+        } else if upper_next {
+            converted.extend(c.to_uppercase());
+            upper_next = false;
+        } else {
+            converted.push(c);
+        }
+    }
+    converted
+}
+
+pub fn camel_case_keys(value: serde_json::Value) -> serde_json::Value {
+    match value {
+        serde_json::Value::Object(map) => serde_json::Value::Object(
+            map.into_iter()
+                .map(|(key, value)| {
+                    let value = if PAYLOAD_FIELDS.contains(&key.as_str()) {
+                        value
+                    } else {
+                        camel_case_keys(value)
+                    };
+                    (camel_case(&key), value)
+                })
+                .collect(),
+        ),
+        serde_json::Value::Array(items) => {
+            serde_json::Value::Array(items.into_iter().map(camel_case_keys).collect())
+        }
+        other => other,
+    }
+}
+
+fn epoch_millis(time: std::time::SystemTime) -> u64 {
+    time.duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn write_attachment(dir: &Path, name: &str, body: &serde_json::Value) -> io::Result<serde_json::Value> {
+    let source = format!("{}-attachment.json", uuid::Uuid::new_v4());
+    fs::write(dir.join(&source), serde_json::to_string_pretty(body)?)?;
+    Ok(json!({ "name": name, "source": source, "type": "application/json" }))
+}
+
+/// Writes one `<uuid>-result.json` per request, in the layout read by `allure generate`.
+pub fn write_allure_results(summary: &TestSummary, dir: &Path) -> io::Result<usize> {
+    fs::create_dir_all(dir)?;
+
+    for result in &summary.results {
+        let start = result.started_at.map(epoch_millis).unwrap_or_else(|| epoch_millis(std::time::SystemTime::now()));
+        let stop = start + result.response_time_ms.round() as u64;
+
+        let status = if result.skipped {
+            "skipped"
+        } else if result.success {
+            "passed"
+        } else if result.status_code.is_some() {
+            "failed"
+        } else {
+            "broken"
+        };
+
+        let mut steps = vec![json!({
+            "name": format!("{} {}", result.method.to_uppercase(), result.url),
+            "status": if result.status_code.is_some() { "passed" } else if result.skipped { "skipped" } else { "broken" },
+            "stage": "finished",
+            "start": start,
+            "stop": stop,
+        })];
+        if let Some(code) = result.status_code {
+            steps.push(json!({
+                "name": format!("Status is 2xx (got {})", code),
+                "status": if (200..300).contains(&code) { "passed" } else { "failed" },
+                "stage": "finished",
+                "start": stop,
+                "stop": stop,
+            }));
+        }
+        for outcome in &result.assertions {
+            steps.push(json!({
+                "name": format!("Assert {}", outcome.name),
+                "status": if outcome.passed { "passed" } else { "failed" },
+                "statusDetails": { "message": outcome.message },
+                "stage": "finished",
+                "start": stop,
+                "stop": stop,
+            }));
+        }
+
+        let mut attachments = Vec::new();
+        if let Some(body) = &result.request_body {
+            attachments.push(write_attachment(dir, "Request body", body)?);
+        }
+        if let Some(body) = &result.response_body {
+            attachments.push(write_attachment(dir, "Response body", body)?);
+        }
+
+        let message = if result.success { None } else { Some(failure_message(result)) };
+        let test_result = json!({
+            "uuid": uuid::Uuid::new_v4().to_string(),
+            "historyId": format!("{} {} {}", result.name, result.method.to_uppercase(), result.url),
+            "name": result.name,
+            "fullName": format!("{} {}", result.method.to_uppercase(), result.name),
+            "status": status,
+            "statusDetails": { "message": message },
+            "stage": "finished",
+            "start": start,
+            "stop": stop,
+            "labels": [
+                { "name": "suite", "value": "jhttp" },
+                { "name": "framework", "value": "jhttp" },
+            ],
+            "parameters": [
+                { "name": "method", "value": result.method.to_uppercase() },
+                { "name": "url", "value": result.url },
+            ],
+            "steps": steps,
+            "attachments": attachments,
+        });
+
+        let file = dir.join(format!("{}-result.json", test_result["uuid"].as_str().unwrap_or_default()));
+        fs::write(file, serde_json::to_string_pretty(&test_result)?)?;
+    }
+
+    Ok(summary.results.len())
+}
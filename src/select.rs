@@ -0,0 +1,92 @@
+//! jq-style paths for `--select`, such as `.data.items[0].id` or `.items[].name`.
+//!
+//! A path is `.` (the whole body) or a chain of `.field`, `["field"]`, `[index]` (negative counts
+//! from the end), and `[]` (every element of an array, or every value of an object).
+
+use serde_json::Value;
+
+#[derive(Debug, Clone)]
+enum Segment {
+    Field(String),
+    Index(i64),
+    Each,
+}
+
+#[derive(Debug, Clone)]
+pub struct Path {
+    source: String,
+    segments: Vec<Segment>,
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+pub fn parse(source: &str) -> Result<Path, String> {
+    let chars: Vec<char> = source.trim().chars().collect();
+    if chars.first() != Some(&'.') {
+        return Err(format!("path '{}' must start with '.'", source));
+    }
+    let mut segments = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '.' if i + 1 == chars.len() && segments.is_empty() => i += 1,
+            '.' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '-') {
+                    i += 1;
+                }
+                if i == start {
+                    if chars.get(i) == Some(&'[') {
+                        continue;
+                    }
+                    return Err(format!("path '{}' has an empty field name", source));
+                }
+                segments.push(Segment::Field(chars[start..i].iter().collect()));
+            }
+            '[' => {
+                let end = chars[i..].iter().position(|&c| c == ']')
+                    .map(|offset| i + offset)
+                    .ok_or_else(|| format!("path '{}' has an unclosed '['", source))?;
+                let inner: String = chars[i + 1..end].iter().collect();
+                let inner = inner.trim();
+                segments.push(if inner.is_empty() {
+                    Segment::Each
+                } else if let Some(field) = inner.strip_prefix('"').and_then(|rest| rest.strip_suffix('"')) {
+                    Segment::Field(field.to_string())
+                } else {
+                    Segment::Index(inner.parse().map_err(|_| format!("path '{}' has an invalid index '{}'", source, inner))?)
+                });
+                i = end + 1;
+            }
+            other => return Err(format!("path '{}' has an unexpected '{}'", source, other)),
+        }
+    }
+    Ok(Path { source: source.trim().to_string(), segments })
+}
+
+/// Every value the path matches in `value`; empty when nothing matches.
+pub fn select<'a>(path: &Path, value: &'a Value) -> Vec<&'a Value> {
+    let mut current = vec![value];
+    for segment in &path.segments {
+        current = current.into_iter()
+            .flat_map(|value| -> Vec<&Value> {
+                match (segment, value) {
+                    (Segment::Field(name), Value::Object(map)) => map.get(name).into_iter().collect(),
+                    (Segment::Index(index), Value::Array(items)) => {
+                        let position = if *index < 0 { items.len() as i64 + index } else { *index };
+                        usize::try_from(position).ok().and_then(|position| items.get(position)).into_iter().collect()
+                    }
+                    (Segment::Each, Value::Array(items)) => items.iter().collect(),
+                    (Segment::Each, Value::Object(map)) => map.values().collect(),
+                    _ => Vec::new(),
+                }
+            })
+            .collect();
+    }
+    current
+}
@@ -0,0 +1,132 @@
+//! Snapshot testing: each response body is compared with the copy stored by an earlier run.
+
+use crate::assertions::AssertionOutcome;
+use crate::diff::{self, Difference};
+use serde::Serialize;
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum SnapshotStatus {
+    /// No snapshot existed, so this response became the snapshot.
+    Created,
+    Matched,
+    /// The response differed and `--update-snapshots` replaced the snapshot.
+    Updated,
+    Mismatch,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct SnapshotResult {
+    pub path: String,
+    pub status: SnapshotStatus,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub differences: Vec<Difference>,
+}
+
+impl SnapshotResult {
+    pub fn outcome(&self) -> AssertionOutcome {
+        let count = match self.differences.len() {
+            1 => "1 difference".to_string(),
+            n => format!("{} differences", n),
+        };
+        let message = match self.status {
+            SnapshotStatus::Created => format!("created {}", self.path),
+            SnapshotStatus::Matched => format!("matches {}", self.path),
+            SnapshotStatus::Updated => format!("updated {} ({})", self.path, count),
+            SnapshotStatus::Mismatch => format!("differs from {} ({})", self.path, count),
+        };
+        AssertionOutcome { name: "snapshot".to_string(), passed: self.status != SnapshotStatus::Mismatch, message }
+    }
+}
+
+/// Removes whatever `segments` (of a JSON pointer) point at; a `*` segment matches every element or key.
+fn remove_path(value: &mut Value, segments: &[String]) {
+    let Some((first, rest)) = segments.split_first() else { return };
+    if rest.is_empty() {
+        match value {
+            Value::Object(map) if first == "*" => map.clear(),
+            Value::Object(map) => {
+                map.remove(first);
+            }
+            Value::Array(items) if first == "*" => items.clear(),
+            Value::Array(items) => {
+                if let Ok(index) = first.parse::<usize>()
+                    && index < items.len()
+                {
+                    items.remove(index);
+                }
+            }
+            _ => {}
+        }
+        return;
+    }
+    match value {
+        Value::Object(map) if first == "*" => map.values_mut().for_each(|child| remove_path(child, rest)),
+        Value::Array(items) if first == "*" => items.iter_mut().for_each(|child| remove_path(child, rest)),
+        Value::Object(map) => {
+            if let Some(child) = map.get_mut(first) {
+                remove_path(child, rest);
+            }
+        }
+        Value::Array(items) => {
+            if let Some(child) = first.parse::<usize>().ok().and_then(|index| items.get_mut(index)) {
+                remove_path(child, rest);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// The body without the volatile fields listed in `ignore_paths`.
+pub fn normalize(body: &Value, ignore_paths: &[String]) -> Value {
+    let mut normalized = body.clone();
+    for pointer in ignore_paths {
+        let segments: Vec<String> = pointer.split('/')
+            .skip(1)
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect();
+        remove_path(&mut normalized, &segments);
+    }
+    normalized
+}
+
+fn write_snapshot(path: &Path, body: &Value) -> Result<(), String> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, format!("{}\n", serde_json::to_string_pretty(body)?))
+    };
+    write().map_err(|err| format!("Failed to write snapshot '{}': {}", path.display(), err))
+}
+
+/// Compares `body` (after dropping `ignore_paths`) with the snapshot at `path`, creating it when
+/// there is none and replacing it on a difference when `update` is set.
+pub fn check(path: &Path, update: bool, body: &Value, ignore_paths: &[String]) -> Result<SnapshotResult, String> {
+    let body = normalize(body, ignore_paths);
+    let result = |status, differences| SnapshotResult { path: path.display().to_string(), status, differences };
+
+    let stored = match fs::read_to_string(path) {
+        Ok(data) => data,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => {
+            write_snapshot(path, &body)?;
+            return Ok(result(SnapshotStatus::Created, Vec::new()));
+        }
+        Err(err) => return Err(format!("Failed to read snapshot '{}': {}", path.display(), err)),
+    };
+    let stored: Value = serde_json::from_str(&stored)
+        .map_err(|err| format!("Invalid snapshot '{}': {}", path.display(), err))?;
+
+    let differences = diff::diff_values(&normalize(&stored, ignore_paths), &body);
+    if differences.is_empty() {
+        Ok(result(SnapshotStatus::Matched, differences))
+    } else if update {
+        write_snapshot(path, &body)?;
+        Ok(result(SnapshotStatus::Updated, differences))
+    } else {
+        Ok(result(SnapshotStatus::Mismatch, differences))
+    }
+}
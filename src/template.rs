@@ -0,0 +1,66 @@
+//! `{{$function}}` placeholders, expanded separately for every request that is sent.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde_json::Value;
+
+static FUNCTION_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\{\{\$(\w+)(?:\(([^)]*)\))?\}\}").unwrap());
+
+fn call(name: &str, args: Option<&str>) -> Result<String, String> {
+    let no_args = |value: String| match args {
+        None => Ok(value),
+        Some(_) => Err(format!("{{{{${}}}}} takes no arguments", name)),
+    };
+    match name {
+        "uuid" => no_args(uuid::Uuid::new_v4().to_string()),
+        "timestamp" => no_args(humantime::format_rfc3339_millis(std::time::SystemTime::now()).to_string()),
+        "unixTimestamp" => no_args(
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs().to_string())
+                .unwrap_or_default(),
+        ),
+        "randomInt" => {
+            let usage = || "{{$randomInt(min,max)}} expects two integers with min <= max".to_string();
+            let (min, max) = args.and_then(|args| args.split_once(',')).ok_or_else(usage)?;
+            let min: i64 = min.trim().parse().map_err(|_| usage())?;
+            let max: i64 = max.trim().parse().map_err(|_| usage())?;
+            if min > max {
+                return Err(usage());
+            }
+            Ok(rand::random_range(min..=max).to_string())
+        }
+        other => Err(format!("Unknown template function '{{{{${}}}}}'", other)),
+    }
+}
+
+/// Replaces every `{{$function}}` in `text`, each occurrence with a fresh value.
+pub fn expand(text: &str) -> Result<String, String> {
+    if !text.contains("{{$") {
+        return Ok(text.to_string());
+    }
+    let mut expanded = String::with_capacity(text.len());
+    let mut last = 0;
+    for caps in FUNCTION_REGEX.captures_iter(text) {
+        let whole = caps.get(0).unwrap();
+        expanded.push_str(&text[last..whole.start()]);
+        expanded.push_str(&call(&caps[1], caps.get(2).map(|args| args.as_str()))?);
+        last = whole.end();
+    }
+    expanded.push_str(&text[last..]);
+    Ok(expanded)
+}
+
+/// Expands the functions in every string of a JSON value (object keys are left alone).
+pub fn expand_value(value: &Value) -> Result<Value, String> {
+    Ok(match value {
+        Value::String(text) => Value::String(expand(text)?),
+        Value::Array(items) => Value::Array(items.iter().map(expand_value).collect::<Result<_, _>>()?),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), expand_value(value)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
@@ -0,0 +1,252 @@
+//! `--interactive`: a terminal browser over the results of a finished run.
+
+use crate::{RequestResult, SECRET_HEADERS};
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+use std::io;
+
+/// How far PageUp/PageDown scroll the detail pane.
+const PAGE_LINES: u16 = 10;
+
+struct Browser<'a> {
+    results: &'a [RequestResult],
+    /// Show Authorization and other secret headers instead of masking them (-vv).
+    show_secrets: bool,
+    filter: String,
+    /// The filter is being typed, after `/`.
+    editing: bool,
+    /// Positions in `results` of the requests whose name contains `filter`.
+    visible: Vec<usize>,
+    list: ListState,
+    scroll: u16,
+}
+
+impl<'a> Browser<'a> {
+    fn new(results: &'a [RequestResult], show_secrets: bool) -> Self {
+        let mut browser = Browser {
+            results,
+            show_secrets,
+            filter: String::new(),
+            editing: false,
+            visible: Vec::new(),
+            list: ListState::default(),
+            scroll: 0,
+        };
+        browser.apply_filter();
+        browser
+    }
+
+    fn apply_filter(&mut self) {
+        let filter = self.filter.to_lowercase();
+        self.visible = (0..self.results.len())
+            .filter(|&index| self.results[index].name.to_lowercase().contains(&filter))
+            .collect();
+        self.list.select((!self.visible.is_empty()).then_some(0));
+        self.scroll = 0;
+    }
+
+    fn selected(&self) -> Option<&'a RequestResult> {
+        let position = *self.visible.get(self.list.selected()?)?;
+        Some(&self.results[position])
+    }
+
+    fn select(&mut self, index: usize) {
+        if !self.visible.is_empty() {
+            self.list.select(Some(index.min(self.visible.len() - 1)));
+            self.scroll = 0;
+        }
+    }
+
+    /// Handles a key press; returns false to quit.
+    fn handle(&mut self, key: KeyEvent) -> bool {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        if self.editing {
+            match key.code {
+                KeyCode::Enter => self.editing = false,
+                KeyCode::Esc => {
+                    self.editing = false;
+                    self.filter.clear();
+                    self.apply_filter();
+                }
+                KeyCode::Backspace => {
+                    self.filter.pop();
+                    self.apply_filter();
+                }
+                KeyCode::Char(c) => {
+                    self.filter.push(c);
+                    self.apply_filter();
+                }
+                _ => {}
+            }
+            return true;
+        }
+        let current = self.list.selected().unwrap_or(0);
+        match key.code {
+            KeyCode::Char('q') => return false,
+            KeyCode::Esc if self.filter.is_empty() => return false,
+            KeyCode::Esc => {
+                self.filter.clear();
+                self.apply_filter();
+            }
+            KeyCode::Char('/') => self.editing = true,
+            KeyCode::Down | KeyCode::Char('j') => self.select(current + 1),
+            KeyCode::Up | KeyCode::Char('k') => self.select(current.saturating_sub(1)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(usize::MAX),
+            KeyCode::PageDown | KeyCode::Char(' ') => self.scroll = self.scroll.saturating_add(PAGE_LINES),
+            KeyCode::PageUp => self.scroll = self.scroll.saturating_sub(PAGE_LINES),
+            _ => {}
+        }
+        true
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, footer] = Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(frame.area());
+        let [list_area, detail_area] = Layout::horizontal([Constraint::Percentage(35), Constraint::Percentage(65)]).areas(main);
+
+        let items: Vec<ListItem> = self.visible.iter().map(|&index| list_item(&self.results[index])).collect();
+        let title = format!(" Requests ({}/{}) ", self.visible.len(), self.results.len());
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let detail = match self.selected() {
+            Some(result) => detail(result, self.show_secrets),
+            None => Text::from("No request matches the filter"),
+        };
+        let detail = Paragraph::new(detail)
+            .block(Block::default().borders(Borders::ALL).title(" Details "))
+            .wrap(Wrap { trim: false })
+            .scroll((self.scroll, 0));
+        frame.render_widget(detail, detail_area);
+
+        let help = if self.editing {
+            format!("/{}▏  Enter apply  Esc clear", self.filter)
+        } else if self.filter.is_empty() {
+            "↑/↓ select  PgUp/PgDn scroll  / filter  q quit".to_string()
+        } else {
+            format!("filter: {}  ↑/↓ select  PgUp/PgDn scroll  / filter  Esc clear  q quit", self.filter)
+        };
+        frame.render_widget(Paragraph::new(help).style(Style::default().fg(Color::DarkGray)), footer);
+    }
+}
+
+fn status_color(result: &RequestResult) -> Color {
+    if result.skipped {
+        Color::Yellow
+    } else if result.success {
+        Color::Green
+    } else {
+        Color::Red
+    }
+}
+
+fn list_item(result: &RequestResult) -> ListItem<'static> {
+    let status = match (result.skipped, result.status_code) {
+        (true, _) => "skip".to_string(),
+        (false, Some(code)) => code.to_string(),
+        (false, None) => "err".to_string(),
+    };
+    ListItem::new(Line::from(vec![
+        Span::styled(format!("{:<4} ", status), Style::default().fg(status_color(result))),
+        Span::styled(format!("{:<6} ", result.method.to_uppercase()), Style::default().fg(Color::DarkGray)),
+        Span::raw(result.name.clone()),
+    ]))
+}
+
+/// Everything known about one request: outcome, assertions, both sides of the exchange, and the body.
+fn detail(result: &RequestResult, show_secrets: bool) -> Text<'static> {
+    let heading = |text: &str| Line::from(Span::styled(text.to_string(), Style::default().add_modifier(Modifier::BOLD)));
+    let mut lines = vec![
+        heading(&result.name),
+        Line::from(format!("{} {}", result.method.to_uppercase(), result.url)),
+    ];
+    let status = match (&result.status_code, &result.status_text) {
+        (Some(code), text) => format!("Status: {} {}", code, text.as_deref().unwrap_or("")),
+        (None, _) if result.skipped => "Skipped".to_string(),
+        (None, _) => "No response".to_string(),
+    };
+    lines.push(Line::from(vec![
+        Span::styled(status, Style::default().fg(status_color(result))),
+        Span::styled(format!("  {:.1} ms", result.response_time_ms), Style::default().fg(Color::DarkGray)),
+    ]));
+    if let Some(error) = &result.error {
+        lines.push(Line::from(Span::styled(error.clone(), Style::default().fg(Color::Red))));
+    }
+
+    if !result.assertions.is_empty() {
+        lines.push(Line::default());
+        lines.push(heading("Assertions"));
+        for outcome in &result.assertions {
+            let (mark, color) = if outcome.passed { ("✓", Color::Green) } else { ("✗", Color::Red) };
+            lines.push(Line::from(Span::styled(format!("{} {}: {}", mark, outcome.name, outcome.message), Style::default().fg(color))));
+        }
+    }
+
+    if let Some(exchange) = &result.exchange {
+        let header_lines = |lines: &mut Vec<Line<'static>>, headers: &[(String, String)]| {
+            for (name, value) in headers {
+                let value = if !show_secrets && SECRET_HEADERS.contains(&name.to_lowercase().as_str()) {
+                    "*** (shown with -vv)".to_string()
+                } else {
+                    value.clone()
+                };
+                lines.push(Line::from(vec![Span::styled(format!("{}: ", name), Style::default().fg(Color::Cyan)), Span::raw(value)]));
+            }
+        };
+        lines.push(Line::default());
+        lines.push(heading("Request"));
+        lines.push(Line::from(exchange.request_line.clone()));
+        header_lines(&mut lines, &exchange.request_headers);
+        if let Some(body) = &exchange.request_body {
+            lines.push(Line::default());
+            lines.extend(body.lines().map(|line| Line::from(line.to_string())));
+        }
+        if let Some(status_line) = &exchange.status_line {
+            lines.push(Line::default());
+            lines.push(heading("Response"));
+            lines.push(Line::from(status_line.clone()));
+            header_lines(&mut lines, &exchange.response_headers);
+        }
+    }
+
+    let body = match (&result.response_body, result.binary_size) {
+        (Some(json), _) => Some(serde_json::to_string_pretty(json).unwrap_or_default()),
+        (None, Some(size)) => Some(format!("<binary, {} bytes>", size)),
+        (None, None) => result.exchange.as_ref().and_then(|exchange| exchange.response_text.clone()).filter(|text| !text.is_empty()),
+    };
+    if let Some(body) = body {
+        lines.push(Line::default());
+        lines.push(heading("Body"));
+        lines.extend(body.lines().map(|line| Line::from(line.to_string())));
+    }
+    Text::from(lines)
+}
+
+fn run(terminal: &mut DefaultTerminal, browser: &mut Browser) -> io::Result<()> {
+    loop {
+        terminal.draw(|frame| browser.draw(frame))?;
+        if let Event::Key(key) = event::read()?
+            && key.kind == KeyEventKind::Press
+            && !browser.handle(key)
+        {
+            return Ok(());
+        }
+    }
+}
+
+/// Shows `results` until the user quits, then restores the terminal.
+pub fn browse(results: &[RequestResult], show_secrets: bool) -> io::Result<()> {
+    let mut browser = Browser::new(results, show_secrets);
+    let mut terminal = ratatui::init();
+    let outcome = run(&mut terminal, &mut browser);
+    ratatui::restore();
+    outcome
+}